@@ -1,4 +1,5 @@
 use super::AccessibilityService;
+use crate::error::AppError;
 use tauri::AppHandle;
 
 pub struct MacAccessibility {
@@ -12,11 +13,11 @@ impl MacAccessibility {
 }
 
 impl AccessibilityService for MacAccessibility {
-    fn get_selected_text(&self) -> Result<String, String> {
+    fn get_selected_text(&self) -> Result<String, AppError> {
         Ok("Selected text from macOS (Mock)".to_string())
     }
 
-    fn replace_selected_text(&self, _text: &str) -> Result<(), String> {
+    fn replace_selected_text(&self, _text: &str) -> Result<(), AppError> {
         Ok(())
     }
 }
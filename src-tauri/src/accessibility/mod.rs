@@ -1,9 +1,10 @@
 
+use crate::error::AppError;
+
 pub trait AccessibilityService {
-    fn get_selected_text(&self) -> Result<String, String>;
-    /// Replaces the current selection with the given text. Reserved for future "apply suggestion" flow.
-    #[allow(dead_code)]
-    fn replace_selected_text(&self, text: &str) -> Result<(), String>;
+    fn get_selected_text(&self) -> Result<String, AppError>;
+    /// Replaces the current selection with the given text. Used when a suggestion is committed.
+    fn replace_selected_text(&self, text: &str) -> Result<(), AppError>;
 }
 
 #[cfg(target_os = "windows")]
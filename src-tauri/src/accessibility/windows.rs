@@ -1,4 +1,5 @@
 use super::AccessibilityService;
+use crate::error::AppError;
 use tauri::AppHandle;
 use tauri_plugin_clipboard_manager::ClipboardExt;
 use std::thread;
@@ -95,7 +96,7 @@ impl WindowsAccessibility {
 }
 
 impl AccessibilityService for WindowsAccessibility {
-    fn get_selected_text(&self) -> Result<String, String> {
+    fn get_selected_text(&self) -> Result<String, AppError> {
         // Clear clipboard first to avoid reading old data? Or just rely on new data.
         // It's safer to clear or verify change.
         // For simplicity:
@@ -106,12 +107,18 @@ impl AccessibilityService for WindowsAccessibility {
         self.send_key_combo(VK_C);
         thread::sleep(Duration::from_millis(100)); // Give app time to copy
 
-        self.app.clipboard().read_text().map_err(|e| e.to_string())
+        self.app
+            .clipboard()
+            .read_text()
+            .map_err(|e| AppError::ClipboardUnavailable(e.to_string()))
     }
 
-    fn replace_selected_text(&self, text: &str) -> Result<(), String> {
+    fn replace_selected_text(&self, text: &str) -> Result<(), AppError> {
         // 1. Write text to clipboard
-        self.app.clipboard().write_text(text).map_err(|e| e.to_string())?;
+        self.app
+            .clipboard()
+            .write_text(text)
+            .map_err(|e| AppError::ClipboardUnavailable(e.to_string()))?;
         
         // 2. Send Ctrl+V
         self.send_key_combo(VK_V);
@@ -0,0 +1,135 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// A named rewrite the popup can run on the selection. Every action answers with the same
+/// `{"corrected": ...}` shape so streaming, chunking and diffing work unchanged; the schema
+/// description of `corrected` tells the model what that text should be.
+#[derive(Serialize, Debug)]
+pub struct Action {
+    pub id: &'static str,
+    pub label: &'static str,
+    /// System prompt. For "fix" the user's custom system prompt takes its place when set.
+    #[serde(skip)]
+    pub system_prompt: &'static str,
+    /// Precedes the text in the user message.
+    #[serde(skip)]
+    pub instruction: &'static str,
+    /// Description of the `corrected` field in the output schema.
+    #[serde(skip)]
+    pub output_description: &'static str,
+    /// Whether a word diff against the original means anything (not for translations).
+    #[serde(skip)]
+    pub diff: bool,
+}
+
+/// Per-run choices for actions that take them. Only "translate" does so far.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ActionOptions {
+    /// Language of the text; None or "auto" lets the model detect it.
+    pub source_language: Option<String>,
+    /// Language to translate into; None uses `default_target_language` from settings.
+    pub target_language: Option<String>,
+    /// Also fix grammar and spelling while translating, instead of translating faithfully.
+    pub fix_grammar: bool,
+}
+
+pub const DEFAULT_ACTION: &str = "fix";
+pub const TRANSLATE_ACTION: &str = "translate";
+
+pub const ACTIONS: &[Action] = &[
+    Action {
+        id: "fix",
+        label: "Fix grammar",
+        system_prompt: super::DEFAULT_SYSTEM_PROMPT,
+        instruction: "Fix the grammar and style of this text:",
+        output_description: "The text with grammar, spelling and punctuation fixed.",
+        diff: true,
+    },
+    Action {
+        id: "formal",
+        label: "Make formal",
+        system_prompt: r#"You rewrite text in a formal, professional register. Keep the meaning, facts and language of the original; fix any grammar mistakes. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the rewritten text>"}"#,
+        instruction: "Rewrite this text in a formal tone:",
+        output_description: "The text rewritten in a formal, professional tone.",
+        diff: true,
+    },
+    Action {
+        id: "casual",
+        label: "Make casual",
+        system_prompt: r#"You rewrite text in a friendly, casual register. Keep the meaning, facts and language of the original; fix any grammar mistakes. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the rewritten text>"}"#,
+        instruction: "Rewrite this text in a casual tone:",
+        output_description: "The text rewritten in a relaxed, conversational tone.",
+        diff: true,
+    },
+    Action {
+        id: "concise",
+        label: "Make concise",
+        system_prompt: r#"You make text shorter. Remove filler, repetition and wordiness while keeping every point and the original language. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the shortened text>"}"#,
+        instruction: "Make this text more concise:",
+        output_description: "A shorter version of the text that keeps every point.",
+        diff: true,
+    },
+    Action {
+        id: "expand",
+        label: "Expand",
+        system_prompt: r#"You expand text. Elaborate on the points it makes with detail and smoother transitions, without inventing facts, and keep the original language and tone. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the expanded text>"}"#,
+        instruction: "Expand this text:",
+        output_description: "A longer, more detailed version of the text.",
+        diff: true,
+    },
+    Action {
+        id: "simplify",
+        label: "Simplify",
+        system_prompt: r#"You simplify text. Use short sentences and common words so anyone can understand it, keeping the meaning and the original language. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the simplified text>"}"#,
+        instruction: "Simplify this text:",
+        output_description: "The text rewritten in plain, simple language.",
+        diff: true,
+    },
+    Action {
+        id: TRANSLATE_ACTION,
+        label: "Translate",
+        // {target} and {grammar} are filled in by `translate_prompts`.
+        system_prompt: r#"You are a translator. Translate the user's text into {target}, keeping its meaning, tone and formatting. {grammar} Also name the language the original is written in, in English. Reply with ONLY a single JSON object (no other text, no markdown) of this exact shape:
+{"corrected": "<the translation>", "detected_language": "<language of the original>"}"#,
+        instruction: "Translate this text into {target}:",
+        output_description: "The translated text.",
+        diff: false,
+    },
+];
+
+/// Looks up an action by ID.
+pub fn find(id: &str) -> Result<&'static Action, AppError> {
+    ACTIONS
+        .iter()
+        .find(|a| a.id == id)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown action '{}'.", id)))
+}
+
+/// System prompt and instruction for the translate action. The target comes from `options`, else
+/// from `default_target`; a named source language replaces detection.
+pub fn translate_prompts(options: &ActionOptions, default_target: &str) -> Result<(String, String), AppError> {
+    let non_empty = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let target = non_empty(&options.target_language)
+        .or_else(|| Some(default_target.trim().to_string()).filter(|s| !s.is_empty()))
+        .ok_or_else(|| AppError::InvalidInput("Choose a language to translate into.".to_string()))?;
+    let source = non_empty(&options.source_language).filter(|s| !s.eq_ignore_ascii_case("auto"));
+
+    let action = find(TRANSLATE_ACTION)?;
+    let grammar = if options.fix_grammar {
+        "Fix grammar, spelling and punctuation mistakes of the original as you translate."
+    } else {
+        "Translate faithfully; do not correct or improve the content."
+    };
+    let system = action.system_prompt.replace("{target}", &target).replace("{grammar}", grammar);
+    let instruction = match source {
+        Some(source) => format!("Translate this text from {} into {}:", source, target),
+        None => action.instruction.replace("{target}", &target),
+    };
+    Ok((system, instruction))
+}
@@ -0,0 +1,191 @@
+use super::{ChatInput, ModelInfo, Provider};
+use crate::settings::GenerationParams;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+pub const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires max_tokens; unless one is configured, this leaves room for long selections.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic Messages API client (`/v1/messages`).
+/// There is no JSON mode, so the output format is ignored and the system prompt alone asks for JSON.
+/// Seeds and presence/frequency penalties are not supported and are not sent. Newer models reject
+/// temperature and top_p together, so only temperature is sent when both are set.
+pub struct Anthropic {
+    base: String,
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+}
+
+impl Anthropic {
+    pub fn new(base: String, api_key: String, model: String, mut params: GenerationParams) -> Self {
+        if params.temperature.is_some() && params.top_p.take().is_some() {
+            log::info!("Anthropic takes temperature or top_p, not both; not sending top_p");
+        }
+        Anthropic {
+            base,
+            api_key,
+            model,
+            params,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base.trim_end_matches('/'), path)
+    }
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    system: &'a str,
+    max_tokens: u32,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    stop_sequences: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    text: String,
+}
+
+// Streamed event, e.g. `{"type":"content_block_delta","delta":{"type":"text_delta","text":"..."}}`
+#[derive(Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    type_: String,
+    delta: Option<StreamDelta>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+    display_name: Option<String>,
+}
+
+impl Provider for Anthropic {
+    fn endpoint(&self) -> String {
+        format!("{} {}", self.base, self.model)
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    fn chat_request(&self, client: &Client, input: &ChatInput) -> RequestBuilder {
+        let req = MessagesRequest {
+            model: &self.model,
+            system: &input.system,
+            max_tokens: self.params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            messages: vec![Message {
+                role: "user",
+                content: &input.user,
+            }],
+            stream: input.stream.then_some(true),
+            temperature: self.params.temperature,
+            top_p: self.params.top_p,
+            stop_sequences: &self.params.stop,
+        };
+        client.post(self.url("messages")).json(&req)
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+    }
+
+    fn parse_response(&self, body: &str) -> Result<String, String> {
+        let resp: MessagesResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        let text: String = resp
+            .content
+            .into_iter()
+            .filter(|b| b.type_ == "text")
+            .map(|b| b.text)
+            .collect();
+        if text.is_empty() {
+            let reason = resp.stop_reason.unwrap_or_else(|| "empty content".to_string());
+            return Err(format!("Anthropic returned no text ({})", reason));
+        }
+        Ok(text)
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Option<String>, String> {
+        let event: StreamEvent = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        match event.type_.as_str() {
+            "content_block_delta" => Ok(event.delta.and_then(|d| d.text)),
+            "error" => Err(format!(
+                "API error in stream: {}",
+                event.error.unwrap_or_default()
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// The listing is paged (20 models by default); the largest page keeps it to one request.
+    fn models_request(&self, client: &Client, page: Option<&str>) -> RequestBuilder {
+        let request = client.get(self.url("models")).query(&[("limit", "1000")]);
+        match page {
+            Some(after_id) => request.query(&[("after_id", after_id)]),
+            None => request,
+        }
+    }
+
+    fn parse_models(&self, body: &str) -> Result<Vec<ModelInfo>, String> {
+        let models: ModelsResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        Ok(models
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                display_name: m.display_name,
+                // Every current Claude model accepts images.
+                vision: Some(true),
+                ..ModelInfo::new(m.id)
+            })
+            .collect())
+    }
+
+    fn next_models_page(&self, body: &str) -> Option<String> {
+        let models: ModelsResponse = serde_json::from_str(body).ok()?;
+        models.last_id.filter(|_| models.has_more)
+    }
+}
@@ -0,0 +1,228 @@
+use std::ops::Range;
+
+/// A piece of the input sent as its own request. `separator` is the whitespace that followed it
+/// in the original text, so joining `text + separator` for every chunk gives the input back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    pub text: &'a str,
+    pub separator: &'a str,
+}
+
+impl Chunk<'_> {
+    /// Whitespace-only chunks (e.g. leading blank lines) are passed through without a request.
+    pub fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+}
+
+/// Where a piece may be cut, from most to least preferred.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Paragraph,
+    Sentence,
+    Word,
+}
+
+/// (body, separator) byte ranges; the separator directly follows the body.
+type Unit = (Range<usize>, Range<usize>);
+
+/// Splits `text` into chunks of at most `max_chars` characters, cutting at paragraph breaks first,
+/// then sentence ends, then spaces, and only mid-word for a single word longer than the limit.
+pub fn split(text: &str, max_chars: usize) -> Vec<Chunk<'_>> {
+    let max_chars = max_chars.max(1);
+    let mut units = Vec::new();
+    split_range(text, 0..text.len(), text.len()..text.len(), Level::Paragraph, max_chars, &mut units);
+
+    let mut chunks = Vec::new();
+    let mut current: Option<(usize, Unit)> = None; // (chunk start, last unit)
+    for unit in units {
+        current = match current {
+            Some((start, _)) if char_len(text, start..unit.0.end) <= max_chars => Some((start, unit)),
+            Some((start, last)) => {
+                chunks.push(chunk(text, start, &last));
+                Some((unit.0.start, unit))
+            }
+            None => Some((unit.0.start, unit)),
+        };
+    }
+    if let Some((start, last)) = current {
+        chunks.push(chunk(text, start, &last));
+    }
+    chunks
+}
+
+fn chunk<'a>(text: &'a str, start: usize, last: &Unit) -> Chunk<'a> {
+    Chunk {
+        text: &text[start..last.0.end],
+        separator: &text[last.1.clone()],
+    }
+}
+
+fn char_len(text: &str, range: Range<usize>) -> usize {
+    text[range].chars().count()
+}
+
+/// Cuts `range` at whitespace runs allowed by `level`, recursing into pieces that are still too
+/// long. The last piece inherits `trailing` as its separator.
+fn split_range(
+    text: &str,
+    range: Range<usize>,
+    trailing: Range<usize>,
+    level: Level,
+    max_chars: usize,
+    out: &mut Vec<Unit>,
+) {
+    let mut pieces: Vec<Unit> = Vec::new();
+    let mut body_start = range.start;
+    for run in whitespace_runs(text, range.clone()) {
+        if is_boundary(text, &run, range.start, level) {
+            pieces.push((body_start..run.start, run.clone()));
+            body_start = run.end;
+        }
+    }
+    pieces.push((body_start..range.end, trailing));
+
+    for (body, separator) in pieces {
+        if char_len(text, body.clone()) <= max_chars {
+            out.push((body, separator));
+            continue;
+        }
+        match level {
+            Level::Paragraph => split_range(text, body, separator, Level::Sentence, max_chars, out),
+            Level::Sentence => split_range(text, body, separator, Level::Word, max_chars, out),
+            Level::Word => split_hard(text, body, separator, max_chars, out),
+        }
+    }
+}
+
+/// Last resort for a single over-long token: cut at character boundaries.
+fn split_hard(text: &str, body: Range<usize>, separator: Range<usize>, max_chars: usize, out: &mut Vec<Unit>) {
+    let mut start = body.start;
+    let mut count = 0;
+    for (i, _) in text[body.clone()].char_indices() {
+        if count == max_chars {
+            let at = body.start + i;
+            out.push((start..at, at..at));
+            start = at;
+            count = 0;
+        }
+        count += 1;
+    }
+    out.push((start..body.end, separator));
+}
+
+fn whitespace_runs(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run_start: Option<usize> = None;
+    for (i, c) in text[range.clone()].char_indices() {
+        let i = range.start + i;
+        match (c.is_whitespace(), run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                runs.push(start..i);
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push(start..range.end);
+    }
+    runs
+}
+
+fn is_boundary(text: &str, run: &Range<usize>, range_start: usize, level: Level) -> bool {
+    match level {
+        Level::Paragraph => text[run.clone()].matches('\n').count() >= 2,
+        Level::Sentence => {
+            // A sentence ends with . ! ? or …, optionally followed by closing quotes/brackets.
+            let before = text[range_start..run.start]
+                .trim_end_matches(['"', '\'', ')', ']', '\u{201D}', '\u{2019}']);
+            before.ends_with(['.', '!', '?', '\u{2026}']) || text[run.clone()].contains('\n')
+        }
+        Level::Word => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(chunks: &[Chunk<'a>]) -> Vec<&'a str> {
+        chunks.iter().map(|c| c.text).collect()
+    }
+
+    fn rejoined(chunks: &[Chunk<'_>]) -> String {
+        chunks.iter().map(|c| format!("{}{}", c.text, c.separator)).collect()
+    }
+
+    #[test]
+    fn short_text_is_one_chunk() {
+        let chunks = split("One sentence. Another one.", 100);
+        assert_eq!(chunks, vec![Chunk { text: "One sentence. Another one.", separator: "" }]);
+    }
+
+    #[test]
+    fn splits_at_paragraph_breaks() {
+        let text = "First paragraph here.\n\nSecond paragraph here.";
+        let chunks = split(text, 30);
+        assert_eq!(texts(&chunks), ["First paragraph here.", "Second paragraph here."]);
+        assert_eq!(chunks[0].separator, "\n\n");
+        assert_eq!(rejoined(&chunks), text);
+    }
+
+    #[test]
+    fn splits_long_paragraph_at_sentence_ends() {
+        let text = "This is one. \"And this is two!\" Three is here?";
+        let chunks = split(text, 20);
+        assert_eq!(texts(&chunks), ["This is one.", "\"And this is two!\"", "Three is here?"]);
+        assert_eq!(rejoined(&chunks), text);
+    }
+
+    #[test]
+    fn fills_chunks_with_several_sentences() {
+        let text = "A b. C d. E f. G h.";
+        let chunks = split(text, 10);
+        assert_eq!(texts(&chunks), ["A b. C d.", "E f. G h."]);
+    }
+
+    #[test]
+    fn splits_long_sentence_at_spaces() {
+        let text = "no sentence end in this line";
+        let chunks = split(text, 10);
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 10));
+        assert_eq!(texts(&chunks), ["no", "sentence", "end in", "this line"]);
+        assert_eq!(rejoined(&chunks), text);
+    }
+
+    #[test]
+    fn hard_splits_a_word_longer_than_the_limit() {
+        let text = "ab äöüßéèà xy";
+        let chunks = split(text, 3);
+        assert_eq!(texts(&chunks), ["ab", "äöü", "ßéè", "à", "xy"]);
+        assert_eq!(chunks[1].separator, "");
+        assert_eq!(rejoined(&chunks), text);
+    }
+
+    #[test]
+    fn leading_blank_lines_are_a_blank_chunk() {
+        let text = "\n\nHello there.\n\nGeneral Kenobi.";
+        let chunks = split(text, 12);
+        assert_eq!(chunks[0], Chunk { text: "", separator: "\n\n" });
+        assert!(chunks[0].is_blank());
+        assert_eq!(rejoined(&chunks), text);
+    }
+
+    #[test]
+    fn joins_corrected_chunks_with_original_separators() {
+        let text = "Frist one.\n\nSecnd one.\n\nThird one.";
+        let chunks = split(text, 12);
+        assert_eq!(chunks.len(), 3);
+        // The last chunk has no result yet and keeps its text.
+        let corrected = vec!["First one.".to_string(), "Second one.".to_string()];
+        assert_eq!(
+            crate::ai::join_chunks(&chunks, &corrected),
+            "First one.\n\nSecond one.\n\nThird one."
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use crate::settings::AppSettings;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// The settings to try in order: the configured provider first, then each fallback with its
+/// provider, base URL, key and model swapped in. Everything else is shared.
+pub fn chain(config: &AppSettings) -> Vec<AppSettings> {
+    let mut chain = vec![config.clone()];
+    chain.extend(config.fallback_providers.iter().map(|fallback| AppSettings {
+        ai_provider: fallback.ai_provider,
+        api_base: fallback.api_base.clone(),
+        api_key: fallback.api_key.clone(),
+        model: fallback.model.clone(),
+        fallback_providers: Vec::new(),
+        ..config.clone()
+    }));
+    chain
+}
+
+#[derive(Default)]
+struct Breaker {
+    /// Unavailable failures since the last success.
+    failures: u32,
+    /// While set and in the future, the provider is skipped.
+    open_until: Option<Instant>,
+}
+
+/// Circuit-breaker state per `Provider::endpoint` for this session.
+fn breakers() -> &'static Mutex<HashMap<String, Breaker>> {
+    static BREAKERS: OnceLock<Mutex<HashMap<String, Breaker>>> = OnceLock::new();
+    BREAKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Whether the endpoint failed too often recently and is still cooling down.
+pub fn is_open(endpoint: &str) -> bool {
+    breakers()
+        .lock()
+        .ok()
+        .and_then(|breakers| breakers.get(endpoint).and_then(|b| b.open_until))
+        .is_some_and(|until| Instant::now() < until)
+}
+
+/// Counts an unavailable failure. After `threshold` of them in a row the endpoint is skipped for
+/// `cooldown`; one more failure after the cool-down opens it again right away. 0 disables this.
+pub fn record_failure(endpoint: &str, threshold: u32, cooldown: Duration) {
+    if let Ok(mut breakers) = breakers().lock() {
+        let breaker = breakers.entry(endpoint.to_string()).or_default();
+        breaker.failures += 1;
+        if threshold > 0 && breaker.failures >= threshold {
+            log::warn!("{} failed {} times, skipping it for {:?}", endpoint, breaker.failures, cooldown);
+            breaker.open_until = Some(Instant::now() + cooldown);
+        }
+    }
+}
+
+pub fn record_success(endpoint: &str) {
+    if let Ok(mut breakers) = breakers().lock() {
+        breakers.remove(endpoint);
+    }
+}
@@ -0,0 +1,61 @@
+use schemars::JsonSchema;
+use schemars::generate::SchemaSettings;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// How strictly a request constrains the reply to JSON, from strictest to loosest.
+/// Endpoints that reject one level with a 400 are retried with the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// JSON matching `ChatInput::schema` (OpenAI `json_schema`, Gemini `responseJsonSchema`).
+    JsonSchema,
+    /// Any valid JSON object (OpenAI `json_object`, Gemini `application/json`).
+    JsonObject,
+    /// No constraint; only the prompt asks for JSON.
+    Text,
+}
+
+impl OutputFormat {
+    /// The next looser level, or None if this is already the loosest.
+    pub fn fallback(self) -> Option<OutputFormat> {
+        match self {
+            OutputFormat::JsonSchema => Some(OutputFormat::JsonObject),
+            OutputFormat::JsonObject => Some(OutputFormat::Text),
+            OutputFormat::Text => None,
+        }
+    }
+}
+
+/// JSON Schema for `T` with all subschemas inlined, since not every endpoint resolves `$ref`s.
+pub fn schema_for<T: JsonSchema>() -> Value {
+    SchemaSettings::draft2020_12()
+        .with(|s| {
+            s.inline_subschemas = true;
+            s.meta_schema = None;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
+
+/// The strictest level each endpoint has accepted during this session, keyed by `Provider::endpoint`.
+fn accepted_formats() -> &'static Mutex<HashMap<String, OutputFormat>> {
+    static FORMATS: OnceLock<Mutex<HashMap<String, OutputFormat>>> = OnceLock::new();
+    FORMATS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The level to start with for an endpoint: whatever worked last time, otherwise the strictest.
+pub fn starting_format(endpoint: &str) -> OutputFormat {
+    accepted_formats()
+        .lock()
+        .ok()
+        .and_then(|formats| formats.get(endpoint).copied())
+        .unwrap_or(OutputFormat::JsonSchema)
+}
+
+pub fn remember_format(endpoint: &str, format: OutputFormat) {
+    if let Ok(mut formats) = accepted_formats().lock() {
+        formats.insert(endpoint.to_string(), format);
+    }
+}
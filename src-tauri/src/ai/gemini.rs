@@ -0,0 +1,249 @@
+use super::format::OutputFormat;
+use super::{ChatInput, ModelInfo, Provider};
+use crate::settings::GenerationParams;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+pub const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Native Google Gemini client (`models/{model}:generateContent`, or `:streamGenerateContent` when streaming).
+/// The key goes in the `x-goog-api-key` header rather than `?key=`, because reqwest errors
+/// include the request URL and we surface those errors to the user.
+pub struct Gemini {
+    base: String,
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+}
+
+impl Gemini {
+    pub fn new(base: String, api_key: String, model: String, params: GenerationParams) -> Self {
+        Gemini {
+            base,
+            api_key,
+            model,
+            params,
+        }
+    }
+
+    /// Model names may be given with or without the "models/" prefix.
+    fn model_path(&self) -> &str {
+        self.model.strip_prefix("models/").unwrap_or(&self.model)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<&'a serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    stop_sequences: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest<'a> {
+    system_instruction: Content<'a>,
+    contents: Vec<Content<'a>>,
+    generation_config: GenerationConfig<'a>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelsResponse {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelEntry {
+    name: String,
+    display_name: Option<String>,
+    input_token_limit: Option<u64>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+impl Provider for Gemini {
+    fn endpoint(&self) -> String {
+        format!("{} {}", self.base, self.model)
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    fn chat_request(&self, client: &Client, input: &ChatInput) -> RequestBuilder {
+        let req = GenerateContentRequest {
+            system_instruction: Content {
+                role: None,
+                parts: vec![Part {
+                    text: input.system.clone(),
+                }],
+            },
+            contents: vec![Content {
+                role: Some("user"),
+                parts: vec![Part {
+                    text: input.user.clone(),
+                }],
+            }],
+            generation_config: GenerationConfig {
+                response_mime_type: (input.format != OutputFormat::Text).then_some("application/json"),
+                response_json_schema: (input.format == OutputFormat::JsonSchema).then_some(&input.schema),
+                temperature: self.params.temperature,
+                top_p: self.params.top_p,
+                max_output_tokens: self.params.max_tokens,
+                seed: self.params.seed,
+                stop_sequences: &self.params.stop,
+                presence_penalty: self.params.presence_penalty,
+                frequency_penalty: self.params.frequency_penalty,
+            },
+        };
+        let method = if input.stream {
+            "streamGenerateContent?alt=sse"
+        } else {
+            "generateContent"
+        };
+        let url = format!(
+            "{}/models/{}:{}",
+            self.base.trim_end_matches('/'),
+            self.model_path(),
+            method
+        );
+        client.post(url).json(&req)
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        request.header("x-goog-api-key", &self.api_key)
+    }
+
+    fn parse_response(&self, body: &str) -> Result<String, String> {
+        let resp: GenerateContentResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        let Some(candidate) = resp.candidates.into_iter().next() else {
+            let reason = resp
+                .prompt_feedback
+                .and_then(|f| f.block_reason)
+                .unwrap_or_else(|| "no candidates".to_string());
+            return Err(format!("Gemini returned no answer ({})", reason));
+        };
+        let text: String = candidate
+            .content
+            .map(|c| c.parts.into_iter().map(|p| p.text).collect())
+            .unwrap_or_default();
+        if text.is_empty() {
+            let reason = candidate.finish_reason.unwrap_or_else(|| "empty content".to_string());
+            return Err(format!("Gemini returned no text ({})", reason));
+        }
+        Ok(text)
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Option<String>, String> {
+        // Each event is a partial GenerateContentResponse.
+        let resp: GenerateContentResponse = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if let Some(reason) = resp.prompt_feedback.and_then(|f| f.block_reason) {
+            return Err(format!("Gemini returned no answer ({})", reason));
+        }
+        Ok(resp
+            .candidates
+            .into_iter()
+            .next()
+            .and_then(|c| c.content)
+            .map(|c| c.parts.into_iter().map(|p| p.text).collect()))
+    }
+
+    /// Models without JSON mode reject the generation config fields, in either spelling.
+    fn rejects_format(&self, body: &str) -> bool {
+        ["response_mime_type", "responseMimeType", "response_json_schema", "responseJsonSchema"]
+            .iter()
+            .any(|field| body.contains(field))
+    }
+
+    /// The listing is paged; the largest page size keeps it to one or two requests.
+    fn models_request(&self, client: &Client, page: Option<&str>) -> RequestBuilder {
+        let request = client
+            .get(format!("{}/models", self.base.trim_end_matches('/')))
+            .query(&[("pageSize", "1000")]);
+        match page {
+            Some(token) => request.query(&[("pageToken", token)]),
+            None => request,
+        }
+    }
+
+    fn parse_models(&self, body: &str) -> Result<Vec<ModelInfo>, String> {
+        let resp: ModelsResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        Ok(resp
+            .models
+            .into_iter()
+            .filter(|m| m.supported_generation_methods.iter().any(|g| g == "generateContent"))
+            .map(|m| ModelInfo {
+                display_name: m.display_name,
+                context_length: m.input_token_limit,
+                // generateContent models all accept responseMimeType "application/json".
+                json_mode: Some(true),
+                ..ModelInfo::new(m.name.strip_prefix("models/").unwrap_or(&m.name).to_string())
+            })
+            .collect())
+    }
+
+    fn next_models_page(&self, body: &str) -> Option<String> {
+        let resp: ModelsResponse = serde_json::from_str(body).ok()?;
+        resp.next_page_token.filter(|token| !token.is_empty())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::AppError;
+use crate::settings::AppSettings;
+use reqwest::{ClientBuilder, Url};
+use std::net::{IpAddr, SocketAddr};
+
+/// An allowlist entry: one address, or a range such as `10.0.0.0/8`.
+struct Range {
+    addr: IpAddr,
+    prefix: u32,
+}
+
+impl Range {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn parse_range(entry: &str) -> Result<Range, AppError> {
+    let invalid = || {
+        AppError::InvalidInput(format!(
+            "Invalid allowlist entry '{}'. Use an IP address or a range such as 192.168.1.0/24.",
+            entry
+        ))
+    };
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (entry, None),
+    };
+    let addr: IpAddr = addr.trim().parse().map_err(|_| invalid())?;
+    let addr = addr.to_canonical();
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.trim().parse().ok().filter(|p| *p <= max).ok_or_else(invalid)?,
+        None => max,
+    };
+    Ok(Range { addr, prefix })
+}
+
+fn parse_allowlist(entries: &[String]) -> Result<Vec<Range>, AppError> {
+    entries
+        .iter()
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .map(parse_range)
+        .collect()
+}
+
+/// Checks that every allowlist entry is an address or range, so a typo is reported when settings
+/// are saved.
+pub fn check_allowlist(entries: &[String]) -> Result<(), AppError> {
+    parse_allowlist(entries).map(|_| ())
+}
+
+/// A client builder for requests to `base`. In local-only mode the host is resolved first and
+/// refused unless every address is loopback or in `local_allowlist`; the client is then pinned to
+/// those addresses, and proxies and redirects are turned off, so nothing can lead elsewhere.
+pub async fn client_builder(config: &AppSettings, base: &str) -> Result<ClientBuilder, AppError> {
+    let builder = reqwest::Client::builder();
+    if !config.local_only {
+        return Ok(builder);
+    }
+    let url = Url::parse(base).map_err(|e| AppError::InvalidInput(format!("Invalid API base URL '{}': {}", base, e)))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::InvalidInput(format!("The API base URL '{}' has no host.", base)))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    // Bracketed IPv6 literals come back with their brackets.
+    let literal = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok();
+    let addrs: Vec<SocketAddr> = match literal {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| AppError::Network(format!("Could not resolve {}: {}", host, e)))?
+            .collect(),
+    };
+
+    let allowlist = parse_allowlist(&config.local_allowlist)?;
+    let allowed = |ip: IpAddr| {
+        let ip = ip.to_canonical();
+        ip.is_loopback() || allowlist.iter().any(|range| range.contains(ip))
+    };
+    if addrs.is_empty() {
+        return Err(AppError::Network(format!("Could not resolve {}.", host)));
+    }
+    if let Some(addr) = addrs.iter().find(|a| !allowed(a.ip())) {
+        let resolved = match literal {
+            Some(_) => String::new(),
+            None => format!(" (resolves to {})", addr.ip()),
+        };
+        return Err(AppError::HostBlocked(format!(
+            "Local-only mode blocked {}{}: it is not on this machine or in the allowlist.",
+            host, resolved
+        )));
+    }
+
+    let builder = builder.no_proxy().redirect(reqwest::redirect::Policy::none());
+    Ok(match literal {
+        Some(_) => builder,
+        None => builder.resolve_to_addrs(host, &addrs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn range(entry: &str) -> Range {
+        parse_range(entry).unwrap()
+    }
+
+    #[test]
+    fn ipv4_prefixes() {
+        let lan = range("192.168.1.0/24");
+        assert!(lan.contains(ip("192.168.1.0")));
+        assert!(lan.contains(ip("192.168.1.255")));
+        assert!(!lan.contains(ip("192.168.2.1")));
+        let ten = range(" 10.0.0.0 / 8 ");
+        assert!(ten.contains(ip("10.255.0.1")));
+        assert!(!ten.contains(ip("11.0.0.1")));
+    }
+
+    #[test]
+    fn single_address_is_a_full_prefix() {
+        let host = range("192.168.1.5");
+        assert_eq!(host.prefix, 32);
+        assert!(host.contains(ip("192.168.1.5")));
+        assert!(!host.contains(ip("192.168.1.6")));
+        assert!(range("192.168.1.5/32").contains(ip("192.168.1.5")));
+        assert!(!range("192.168.1.5/32").contains(ip("192.168.1.4")));
+    }
+
+    #[test]
+    fn prefix_zero_matches_the_whole_family() {
+        assert!(range("0.0.0.0/0").contains(ip("8.8.8.8")));
+        assert!(!range("0.0.0.0/0").contains(ip("2001:db8::1")));
+        assert!(range("::/0").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv6_prefixes() {
+        let ula = range("fd00::/8");
+        assert!(ula.contains(ip("fd12:3456::1")));
+        assert!(!ula.contains(ip("fe80::1")));
+        let host = range("fd00::1");
+        assert_eq!(host.prefix, 128);
+        assert!(host.contains(ip("fd00::1")));
+        assert!(!host.contains(ip("fd00::2")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_are_compared_as_ipv4() {
+        // Entries are canonicalized when parsed; resolved addresses by the caller.
+        let mapped = range("::ffff:192.168.1.0/24");
+        assert!(mapped.addr.is_ipv4());
+        assert!(mapped.contains(ip("192.168.1.7")));
+        assert!(range("192.168.1.0/24").contains(ip("::ffff:192.168.1.7").to_canonical()));
+        assert!(!range("192.168.1.0/24").contains(ip("::ffff:192.168.1.7")));
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        for entry in ["192.168.1.0/33", "::1/129", "192.168.1.0/", "192.168.1.0/x", "localhost", "300.1.1.1"] {
+            assert!(
+                matches!(parse_range(entry), Err(AppError::InvalidInput(_))),
+                "{} should be rejected",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn blank_allowlist_entries_are_skipped() {
+        assert!(check_allowlist(&[String::new(), " ".to_string(), "10.0.0.0/8".to_string()]).is_ok());
+        assert!(check_allowlist(&["10.0.0.0/40".to_string()]).is_err());
+    }
+}
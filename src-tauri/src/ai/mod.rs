@@ -0,0 +1,1008 @@
+pub mod actions;
+mod anthropic;
+mod chunk;
+mod fallback;
+mod format;
+mod gemini;
+mod local;
+mod ollama;
+mod openai;
+mod parse;
+pub mod pii;
+mod protect;
+mod retry;
+mod stream;
+
+use crate::diff::{self, DiffSpan};
+use crate::error::AppError;
+use crate::redact;
+use crate::settings::{AppSettings, ProviderKind};
+use actions::{Action, ActionOptions};
+use anthropic::Anthropic;
+use chunk::Chunk;
+use format::OutputFormat;
+use futures_util::StreamExt;
+use gemini::Gemini;
+use ollama::Ollama;
+use openai::{MaxTokensField, OpenAiCompatible};
+use protect::Masked;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use retry::RetryPolicy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub use local::check_allowlist;
+pub use protect::check_patterns;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Correction {
+    pub original: String,
+    pub corrected: String,
+    /// Plain-text summary of the edits (one reason per line), when explanations were requested.
+    pub explanation: Option<String>,
+    /// The changes the model reports having made. Empty unless explanations were requested.
+    #[serde(default)]
+    pub edits: Vec<Edit>,
+    /// Word-level changes computed locally from `original` and `corrected`.
+    #[serde(default)]
+    pub diff: Vec<DiffSpan>,
+    /// Chunks of a long selection that could not be fixed; their original text is kept.
+    #[serde(default)]
+    pub chunk_errors: Vec<ChunkError>,
+    /// True when this answer came from the response cache instead of the provider.
+    #[serde(default)]
+    pub cached: bool,
+    /// Language of the original as reported by the model, for translations.
+    #[serde(default)]
+    pub detected_language: Option<String>,
+    /// Labeled alternatives when more than one suggestion was requested; `corrected` is the first.
+    #[serde(default)]
+    pub alternatives: Vec<Candidate>,
+    /// Provider that answered, as its base URL and model.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// True when the answer came from a fallback because the main provider was unavailable.
+    #[serde(default)]
+    pub fallback: bool,
+    /// Personal data and secrets replaced by placeholders before sending.
+    #[serde(default)]
+    pub redactions: Vec<Redaction>,
+}
+
+/// How many items of one kind were redacted from the text before it was sent.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Redaction {
+    /// Detector id, e.g. "email".
+    pub kind: String,
+    /// Plural name, e.g. "email addresses".
+    pub label: String,
+    pub count: usize,
+}
+
+/// One of several suggested versions of the text.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Candidate {
+    /// How this version differs, e.g. "minimal edit", "more fluent", "shorter".
+    pub label: String,
+    pub corrected: String,
+    /// Word-level changes from the original, as for `Correction::diff`.
+    #[serde(default)]
+    pub diff: Vec<DiffSpan>,
+}
+
+/// A chunk of a split selection whose request failed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChunkError {
+    /// Zero-based position of the chunk in the selection.
+    pub index: usize,
+    /// Names the chunk ("Chunk 2 of 5 failed: ...") and the underlying error, with secrets scrubbed.
+    pub message: String,
+}
+
+/// What kind of change an edit is.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditCategory {
+    Spelling,
+    Grammar,
+    Punctuation,
+    Style,
+    Clarity,
+    /// Anything the model labels outside the list above; not offered in the schema.
+    #[serde(other)]
+    #[schemars(skip)]
+    Other,
+}
+
+/// One change reported by the model: the span it replaced, the replacement and why.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[schemars(deny_unknown_fields)]
+pub struct Edit {
+    pub original: String,
+    pub replacement: String,
+    pub category: EditCategory,
+    /// A one-line reason for the change.
+    pub reason: String,
+}
+
+/// One request to a provider: system prompt, user message, how strictly to ask for JSON
+/// (with the schema used at the strictest level) and whether to stream the response.
+pub struct ChatInput {
+    pub system: String,
+    pub user: String,
+    pub format: OutputFormat,
+    pub schema: serde_json::Value,
+    pub stream: bool,
+}
+
+/// A model advertised by a provider's listing endpoint. Capability fields are None when the
+/// provider does not report them.
+#[derive(Serialize, Debug)]
+pub struct ModelInfo {
+    /// The name to put in the `model` setting.
+    pub id: String,
+    pub display_name: Option<String>,
+    /// Maximum input tokens.
+    pub context_length: Option<u64>,
+    /// Whether the model can be constrained to JSON output.
+    pub json_mode: Option<bool>,
+    /// Whether the model accepts images.
+    pub vision: Option<bool>,
+}
+
+impl ModelInfo {
+    pub fn new(id: String) -> Self {
+        ModelInfo {
+            id,
+            display_name: None,
+            context_length: None,
+            json_mode: None,
+            vision: None,
+        }
+    }
+}
+
+/// A chat backend. Each implementation owns its wire format (URL, body, auth, response shape);
+/// the request loop in this module is shared by all of them.
+pub trait Provider: Send + Sync {
+    /// Identifies the endpoint and model, used to remember what each endpoint accepts.
+    fn endpoint(&self) -> String;
+
+    /// Where requests go, for the local-only check.
+    fn base_url(&self) -> &str;
+
+    /// Builds the completion request (URL and body) without credentials.
+    fn chat_request(&self, client: &Client, input: &ChatInput) -> RequestBuilder;
+
+    /// Adds the provider's credentials to an outgoing request.
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder;
+
+    // Parsing methods return a plain reason; callers turn it into `AppError::InvalidResponse`.
+
+    /// Extracts the assistant text from a successful completion response body.
+    fn parse_response(&self, body: &str) -> Result<String, String>;
+
+    /// Turns an error response into a specific message, or None for the generic
+    /// "API error {status}: {body}".
+    fn describe_error(&self, _status: StatusCode, _body: &str) -> Option<String> {
+        None
+    }
+
+    /// Whether a 400 body says the requested output format was the problem, so the request is
+    /// retried with a looser one. Providers that never send the format keep the default.
+    fn rejects_format(&self, _body: &str) -> bool {
+        false
+    }
+
+    /// Extracts the text delta from one streamed event payload (the part after `data:`).
+    /// Returns None for events that carry no text (pings, stop markers, metadata).
+    fn parse_stream_event(&self, data: &str) -> Result<Option<String>, String>;
+
+    /// Builds the request that lists available models, continuing at `page` (a token from
+    /// `next_models_page`) when the listing is paged.
+    fn models_request(&self, client: &Client, page: Option<&str>) -> RequestBuilder;
+
+    /// Parses the model listing response.
+    fn parse_models(&self, body: &str) -> Result<Vec<ModelInfo>, String>;
+
+    /// The token for the next page of a model listing, or None on the last page.
+    fn next_models_page(&self, _body: &str) -> Option<String> {
+        None
+    }
+
+    /// Builds a per-model request for capabilities the listing leaves out, if the provider has one.
+    fn model_detail_request(&self, _client: &Client, _id: &str) -> Option<RequestBuilder> {
+        None
+    }
+
+    /// Fills in `model` from the response to `model_detail_request`.
+    fn parse_model_detail(&self, _body: &str, _model: &mut ModelInfo) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Builds the minimal request used by "Test connection".
+    fn probe_request(&self, client: &Client) -> RequestBuilder {
+        let input = ChatInput {
+            system: "Reply with only the word OK.".to_string(),
+            user: "test".to_string(),
+            format: OutputFormat::Text,
+            schema: serde_json::Value::Null,
+            stream: false,
+        };
+        self.chat_request(client, &input)
+    }
+
+    /// Checks that a probe response looks like a working completion.
+    fn check_probe(&self, body: &str) -> Result<(), String> {
+        self.parse_response(body).map(|_| ())
+    }
+}
+
+const LOCAL_API_BASE: &str = "http://localhost:11434/v1";
+
+/// Builds the provider selected in settings, applying env-var fallbacks for empty fields.
+/// OpenAI, Gemini and Anthropic use fixed URLs; Ollama and Custom use the user's api_base.
+/// Ollama is spoken to natively, Custom through the OpenAI-compatible protocol.
+pub fn provider_for(config: &AppSettings) -> Box<dyn Provider> {
+    let key = if config.api_key.is_empty() {
+        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "ollama".to_string())
+    } else {
+        config.api_key.clone()
+    };
+    let model = |default: &str| {
+        if config.model.is_empty() {
+            std::env::var("OPENAI_MODEL").unwrap_or_else(|_| default.to_string())
+        } else {
+            config.model.clone()
+        }
+    };
+    let user_base = || {
+        let base = config.api_base.trim();
+        if base.is_empty() {
+            std::env::var("OPENAI_API_BASE").unwrap_or_else(|_| LOCAL_API_BASE.to_string())
+        } else {
+            base.to_string()
+        }
+    };
+
+    let params = config.generation.clone();
+    match config.ai_provider {
+        ProviderKind::OpenAi => Box::new(OpenAiCompatible::new(
+            openai::OPENAI_API_BASE.to_string(),
+            key,
+            model("gpt-5-nano"),
+            params,
+            MaxTokensField::MaxCompletionTokens,
+        )),
+        ProviderKind::Gemini => Box::new(Gemini::new(
+            gemini::GEMINI_API_BASE.to_string(),
+            key,
+            model("gemini-3-flash-preview"),
+            params,
+        )),
+        ProviderKind::Anthropic => Box::new(Anthropic::new(
+            anthropic::ANTHROPIC_API_BASE.to_string(),
+            key,
+            model("claude-haiku-4-5"),
+            params,
+        )),
+        ProviderKind::Ollama => Box::new(Ollama::new(
+            user_base(),
+            key,
+            model("gemma3"),
+            &params,
+            &config.ollama_keep_alive,
+            config.ollama_num_ctx,
+            config.ollama_options.clone(),
+        )),
+        ProviderKind::Custom => Box::new(OpenAiCompatible::new(
+            user_base(),
+            key,
+            model("gemma3"),
+            params,
+            MaxTokensField::MaxTokens,
+        )),
+    }
+}
+
+/// The error for a non-success response whose body has already been read.
+fn status_error(
+    provider: &dyn Provider,
+    status: StatusCode,
+    retry_after: Option<std::time::Duration>,
+    body: &str,
+) -> AppError {
+    let message = provider
+        .describe_error(status, body)
+        .unwrap_or_else(|| format!("API error {}: {}", status, body));
+    AppError::from_status(status, retry_after, message)
+}
+
+/// Turns a non-success response into an error typed by its status, carrying the body.
+async fn check_status(provider: &dyn Provider, response: Response) -> Result<Response, AppError> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let retry_after = retry::server_delay(status, response.headers());
+        let body = response.text().await.unwrap_or_default();
+        return Err(status_error(provider, status, retry_after, &body));
+    }
+    Ok(response)
+}
+
+/// Sends an authenticated request (retrying transient failures) and returns the body of a
+/// successful response.
+async fn send(
+    provider: &dyn Provider,
+    retry: &RetryPolicy,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<String, AppError> {
+    let response = retry::send_with_retry(retry, || provider.authenticate(build()))
+        .await
+        .map_err(AppError::from_transport)?;
+    let response = check_status(provider, response).await?;
+    response.text().await.map_err(AppError::from_transport)
+}
+
+/// Sends a chat request, stepping down from `input.format` to looser output formats while the
+/// endpoint answers 400 because of the format. The level that finally works is remembered for
+/// the endpoint; any other 400 is returned as an error.
+async fn send_chat(
+    provider: &dyn Provider,
+    client: &Client,
+    retry: &RetryPolicy,
+    input: &mut ChatInput,
+) -> Result<Response, AppError> {
+    let endpoint = provider.endpoint();
+    loop {
+        let response = retry::send_with_retry(retry, || provider.authenticate(provider.chat_request(client, input)))
+            .await
+            .map_err(AppError::from_transport)?;
+
+        let status = response.status();
+        if status == StatusCode::BAD_REQUEST
+            && let Some(next) = input.format.fallback()
+        {
+            let retry_after = retry::server_delay(status, response.headers());
+            let body = response.text().await.unwrap_or_default();
+            if !provider.rejects_format(&body) {
+                return Err(status_error(provider, status, retry_after, &body));
+            }
+            log::info!("{} rejected {:?}, retrying with {:?}", endpoint, input.format, next);
+            input.format = next;
+            continue;
+        }
+
+        let response = check_status(provider, response).await?;
+        format::remember_format(&endpoint, input.format);
+        return Ok(response);
+    }
+}
+
+/// Structured output schema: the corrected text, plus the edit list when explanations are on.
+/// Also sent as the JSON Schema for endpoints that support `json_schema` output.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct GrammarResponse {
+    corrected: String,
+    #[serde(default)]
+    #[schemars(skip)]
+    edits: Vec<Edit>,
+    #[serde(default)]
+    #[schemars(skip)]
+    detected_language: Option<String>,
+    #[serde(default)]
+    #[schemars(skip)]
+    alternatives: Vec<Alternative>,
+}
+
+/// Schema sent when explanations are requested. Strict schemas need every property required,
+/// so this is a separate type; replies are still parsed as `GrammarResponse`.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+#[allow(dead_code)]
+struct ExplainedGrammarResponse {
+    corrected: String,
+    edits: Vec<Edit>,
+}
+
+/// One entry of the `alternatives` array.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Alternative {
+    /// Short description of how this version differs, e.g. "minimal edit", "more fluent", "shorter".
+    label: String,
+    corrected: String,
+}
+
+/// Schema and reply when several alternatives are requested.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct AlternativesResponse {
+    alternatives: Vec<Alternative>,
+}
+
+/// Schema sent by the translate action; replies are parsed as `GrammarResponse`.
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+#[allow(dead_code)]
+struct TranslationResponse {
+    corrected: String,
+    /// English name of the language the original text is written in.
+    detected_language: String,
+}
+
+/// System prompt: we ask for a JSON schema first, but fall back to "json_object" (which only enforces
+/// "valid JSON object", not which keys) or no format at all on endpoints that reject schemas.
+/// So we still specify the shape here; otherwise the model might use different keys.
+const DEFAULT_SYSTEM_PROMPT: &str = r#"You are a grammar and style fixer. Reply with a single JSON object only. Use this exact shape:
+{"corrected": "<the corrected text>"}
+Output nothing else. No explanation, no markdown."#;
+
+/// Appended to the system prompt when several alternatives are requested; replaces the single-answer shape.
+const ALTERNATIVES_PROMPT: &str = r#"Instead of a single answer, give exactly {n} different versions, each with a short label saying how it differs (for example "minimal edit", "more fluent", "shorter"). Put the most conservative version first. Reply with ONLY this JSON shape:
+{"alternatives": [{"label": "<label>", "corrected": "<the text>"}]}"#;
+
+/// System prompt of the one retry that asks the model to turn an unreadable answer into JSON.
+const REPAIR_PROMPT: &str = "The user's message is an answer that should have been a JSON object but could not be parsed. Rewrite it as a single JSON object matching this JSON Schema, keeping its content unchanged. Drop any reasoning, preamble or commentary. Reply with ONLY the JSON object.\n{schema}";
+
+/// Appended to the system prompt when protected spans were masked.
+const PROTECTED_PROMPT: &str = "Tokens like {token} stand for code, links, names, placeholders and redacted personal data that must not change. Copy every token exactly once, unchanged, in the place it belongs.";
+
+/// Appended to the system prompt when explanations are requested.
+const EXPLAIN_PROMPT: &str = r#"Also list every change you made in an "edits" array next to "corrected":
+{"corrected": "<the corrected text>", "edits": [{"original": "<text you replaced>", "replacement": "<what you replaced it with>", "category": "spelling" | "grammar" | "punctuation" | "style" | "clarity", "reason": "<one short sentence>"}]}
+Use an empty array if you changed nothing."#;
+
+/// Everything a request needs that is the same for every chunk of the input.
+struct FixRequest<'a> {
+    provider: &'a dyn Provider,
+    client: &'a Client,
+    retry: &'a RetryPolicy,
+    system_prompt: &'a str,
+    /// Precedes the text in the user message.
+    instruction: &'a str,
+    schema: &'a serde_json::Value,
+    /// Whether replies use the `alternatives` array shape.
+    alternatives: bool,
+    stream: bool,
+    /// Log only the length and hash of texts, not the texts.
+    privacy: bool,
+}
+
+/// Runs `action` on `text`. `on_progress` receives the partial result while it arrives: streamed
+/// tokens when `config.stream_responses` is set, or finished chunks when the text is split.
+///
+/// When a provider is unavailable the fallback providers are tried in order. Providers whose
+/// circuit breaker is open are skipped, unless that would leave none to try.
+pub async fn run_action_with_config(
+    action: &Action,
+    options: &ActionOptions,
+    text: String,
+    config: &AppSettings,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<Correction, AppError> {
+    if text.is_empty() {
+        return Err(AppError::InvalidInput("No text to fix after filtering.".to_string()));
+    }
+    let masked = protect::mask(&text, config)?;
+    if !masked.redactions.is_empty() {
+        let counts: Vec<String> = masked.redactions.iter().map(|r| format!("{} {}", r.count, r.kind)).collect();
+        log::info!("Redacted before sending: {}", counts.join(", "));
+    }
+    let on_progress = |partial: &str| on_progress(&masked.restore_lenient(partial));
+    let correction = run_with_fallback(action, options, &masked, config, &on_progress).await?;
+    match unmask(correction, &masked, action, text.clone()) {
+        // Models now and then drop or repeat a token; a second answer usually keeps them all.
+        Err(e) => {
+            log::warn!("{} Asking once more.", e);
+            let correction = run_with_fallback(action, options, &masked, config, &on_progress).await?;
+            unmask(correction, &masked, action, text)
+        }
+        result => result,
+    }
+}
+
+/// Tries each provider of the fallback chain in turn until one is not unavailable.
+async fn run_with_fallback(
+    action: &Action,
+    options: &ActionOptions,
+    masked: &Masked,
+    config: &AppSettings,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<Correction, AppError> {
+    let chain = fallback::chain(config);
+    let endpoints: Vec<String> = chain.iter().map(|c| provider_for(c).endpoint()).collect();
+    let mut order: Vec<usize> = (0..chain.len()).filter(|&i| !fallback::is_open(&endpoints[i])).collect();
+    if order.is_empty() {
+        order = (0..chain.len()).collect();
+    }
+    let cooldown = std::time::Duration::from_secs(config.breaker_cooldown_secs);
+
+    let mut last_error = None;
+    for i in order {
+        match run_with_provider(action, options, masked, &chain[i], on_progress).await {
+            Ok(mut correction) => {
+                fallback::record_success(&endpoints[i]);
+                correction.provider = Some(endpoints[i].clone());
+                correction.fallback = i > 0;
+                return Ok(correction);
+            }
+            Err(e) if e.is_unavailable() => {
+                log::warn!("{} unavailable: {}", endpoints[i], e);
+                fallback::record_failure(&endpoints[i], config.breaker_threshold, cooldown);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let last_error = last_error.expect("the chain has at least one provider");
+    if chain.len() > 1 {
+        return Err(last_error.context("No provider could be reached. Last error: "));
+    }
+    Err(last_error)
+}
+
+/// Puts the protected spans back into the answer and computes the diffs against the original.
+/// Fails when the model dropped or repeated a protected span or redaction placeholder.
+fn unmask(mut correction: Correction, masked: &Masked, action: &Action, original: String) -> Result<Correction, AppError> {
+    let diff_from_original = |corrected: &str| {
+        if action.diff {
+            diff::diff_words(&original, corrected)
+        } else {
+            Vec::new()
+        }
+    };
+    correction.corrected = masked.restore(&correction.corrected)?;
+    correction.diff = diff_from_original(&correction.corrected);
+    for candidate in &mut correction.alternatives {
+        candidate.corrected = masked.restore(&candidate.corrected)?;
+        candidate.diff = diff_from_original(&candidate.corrected);
+    }
+    for edit in &mut correction.edits {
+        edit.original = masked.restore_lenient(&edit.original);
+        edit.replacement = masked.restore_lenient(&edit.replacement);
+        edit.reason = masked.restore_lenient(&edit.reason);
+    }
+    correction.explanation = correction.explanation.map(|e| masked.restore_lenient(&e));
+    correction.redactions = masked.redactions.clone();
+    correction.original = original;
+    Ok(correction)
+}
+
+/// Runs `action` on the masked text with the provider from `config` alone. Diffs are left empty
+/// for `unmask` to fill in.
+async fn run_with_provider(
+    action: &Action,
+    options: &ActionOptions,
+    masked: &Masked,
+    config: &AppSettings,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<Correction, AppError> {
+    let provider = provider_for(config);
+    let text = masked.text.as_str();
+    let chunks = chunk::split(text, config.chunk_size);
+    // Alternatives cannot be lined up across chunks, so split text gets a single answer.
+    let alternatives = if chunks.len() == 1 { config.alternatives } else { 1 };
+    let prompt = prompt_for(config, action, options, alternatives, masked.example_token())?;
+
+    // Only a single request is streamed; split text reports progress per finished chunk instead.
+    let stream = config.stream_responses && chunks.len() == 1;
+    let builder = local::client_builder(config, provider.base_url()).await?;
+    // A streamed answer may legitimately take longer than 60s in total, so only bound the gaps.
+    let client = if stream {
+        builder
+            .connect_timeout(std::time::Duration::from_secs(15))
+            .read_timeout(std::time::Duration::from_secs(60))
+    } else {
+        builder.timeout(std::time::Duration::from_secs(60))
+    }
+    .build()
+    .map_err(AppError::from_transport)?;
+
+    let retry = RetryPolicy::from_settings(config);
+    let request = FixRequest {
+        provider: provider.as_ref(),
+        client: &client,
+        retry: &retry,
+        system_prompt: &prompt.system,
+        instruction: &prompt.instruction,
+        schema: &prompt.schema,
+        alternatives: prompt.alternatives,
+        stream,
+        privacy: config.privacy_mode,
+    };
+    let (result, chunk_errors) = if chunks.len() == 1 {
+        (fix_text(&request, text, on_progress).await?, Vec::new())
+    } else {
+        fix_chunks(&request, &chunks, config.chunk_concurrency, on_progress).await?
+    };
+    let GrammarResponse {
+        corrected,
+        edits,
+        detected_language,
+        alternatives,
+    } = result;
+
+    let explanation = (!edits.is_empty()).then(|| {
+        edits
+            .iter()
+            .map(|e| e.reason.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    let alternatives = alternatives
+        .into_iter()
+        .map(|a| Candidate {
+            label: a.label,
+            corrected: a.corrected,
+            diff: Vec::new(),
+        })
+        .collect();
+
+    Ok(Correction {
+        original: text.to_string(),
+        corrected,
+        explanation,
+        edits,
+        diff: Vec::new(),
+        chunk_errors,
+        cached: false,
+        detected_language,
+        alternatives,
+        provider: None,
+        fallback: false,
+        redactions: Vec::new(),
+    })
+}
+
+/// What to send for one action: system prompt, the instruction before the text, output schema.
+struct ActionPrompt {
+    system: String,
+    instruction: String,
+    schema: serde_json::Value,
+    /// Whether the schema asks for an `alternatives` array.
+    alternatives: bool,
+}
+
+/// Builds the prompt for `action`. The custom system prompt from settings and edit explanations
+/// only apply to the default "fix" action; translations and explained fixes are never offered
+/// as alternatives. `protected_token` is one of the masking tokens in the text, if any.
+fn prompt_for(
+    config: &AppSettings,
+    action: &Action,
+    options: &ActionOptions,
+    alternatives: usize,
+    protected_token: Option<&str>,
+) -> Result<ActionPrompt, AppError> {
+    let is_fix = action.id == actions::DEFAULT_ACTION;
+    let (mut system, instruction) = if action.id == actions::TRANSLATE_ACTION {
+        actions::translate_prompts(options, &config.default_target_language)?
+    } else if is_fix && !config.system_prompt.is_empty() {
+        (config.system_prompt.clone(), action.instruction.to_string())
+    } else {
+        (action.system_prompt.to_string(), action.instruction.to_string())
+    };
+    let mut use_alternatives = false;
+    let mut schema = if action.id == actions::TRANSLATE_ACTION {
+        format::schema_for::<TranslationResponse>()
+    } else if is_fix && config.explain_edits {
+        system.push_str("\n\n");
+        system.push_str(EXPLAIN_PROMPT);
+        format::schema_for::<ExplainedGrammarResponse>()
+    } else if alternatives > 1 {
+        use_alternatives = true;
+        system.push_str("\n\n");
+        system.push_str(&ALTERNATIVES_PROMPT.replace("{n}", &alternatives.to_string()));
+        format::schema_for::<AlternativesResponse>()
+    } else {
+        format::schema_for::<GrammarResponse>()
+    };
+    if let Some(token) = protected_token {
+        system.push_str("\n\n");
+        system.push_str(&PROTECTED_PROMPT.replace("{token}", token));
+    }
+    if use_alternatives {
+        let items = &mut schema["properties"]["alternatives"];
+        items["minItems"] = alternatives.into();
+        items["maxItems"] = alternatives.into();
+        items["items"]["properties"]["corrected"]["description"] = action.output_description.into();
+    } else {
+        schema["properties"]["corrected"]["description"] = action.output_description.into();
+    }
+    Ok(ActionPrompt {
+        system,
+        instruction,
+        schema,
+        alternatives: use_alternatives,
+    })
+}
+
+/// Identifies a request for the response cache: the text plus everything that changes the answer
+/// (action, provider, resolved endpoint and model, effective system prompt, request parameters).
+pub fn cache_key(text: &str, config: &AppSettings, action: &Action, options: &ActionOptions) -> String {
+    use sha2::{Digest, Sha256};
+
+    let prompt = prompt_for(config, action, options, config.alternatives, None).ok();
+    let key = serde_json::json!({
+        "action": action.id,
+        "provider": config.ai_provider,
+        "endpoint": provider_for(config).endpoint(),
+        "system_prompt": prompt.as_ref().map(|p| &p.system),
+        "instruction": prompt.as_ref().map(|p| &p.instruction),
+        "explain_edits": config.explain_edits,
+        "chunk_size": config.chunk_size,
+        "alternatives": config.alternatives,
+        "protected": [&config.protect_spans, &config.protected_patterns],
+        "redacted": [&config.pii_redaction, &config.pii_detectors],
+        "generation": config.generation,
+        "ollama_options": [&config.ollama_num_ctx, &config.ollama_options],
+        "text": text,
+    });
+    Sha256::digest(key.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Sends one piece of text and returns the corrected text with anything else the model reported.
+async fn fix_text(
+    request: &FixRequest<'_>,
+    text: &str,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<GrammarResponse, AppError> {
+    let provider = request.provider;
+    let mut input = ChatInput {
+        system: request.system_prompt.to_string(),
+        user: format!("{}\n\n{}", request.instruction, text),
+        format: format::starting_format(&provider.endpoint()),
+        schema: request.schema.clone(),
+        stream: request.stream,
+    };
+
+    log::debug!("Request: {}", loggable(request, &input.user));
+
+    let content = complete(request, &mut input, on_progress).await?;
+    match parse_reply(&content, request.alternatives) {
+        Ok(reply) => Ok(reply),
+        Err(reason) => {
+            log::warn!("Unreadable answer ({}), asking for a repair", reason);
+            let mut repair = ChatInput {
+                system: REPAIR_PROMPT.replace("{schema}", &request.schema.to_string()),
+                user: content.clone(),
+                format: input.format,
+                schema: request.schema.clone(),
+                stream: false,
+            };
+            let repaired = complete(request, &mut repair, &|_| {}).await?;
+            parse_reply(&repaired, request.alternatives).map_err(|reason| AppError::InvalidResponse {
+                message: format!("The model's answer could not be read ({}).", reason),
+                raw: Some(content),
+            })
+        }
+    }
+}
+
+/// Sends `input` and returns the model's text, streamed or not as `input.stream` says.
+async fn complete(
+    request: &FixRequest<'_>,
+    input: &mut ChatInput,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<String, AppError> {
+    let provider = request.provider;
+    let started = Instant::now();
+    let response = send_chat(provider, request.client, request.retry, input).await?;
+    let content = if input.stream {
+        let on_text = |content: &str| {
+            if let Some(partial) = stream::partial_corrected(content) {
+                on_progress(&partial);
+            }
+        };
+        stream::read_event_stream(provider, response, &on_text).await?
+    } else {
+        let body = response.text().await.map_err(AppError::from_transport)?;
+        provider.parse_response(&body).map_err(AppError::invalid_response)?
+    };
+    let content = content.trim().to_string();
+    log::info!(
+        "Answer after {} ms: {}",
+        started.elapsed().as_millis(),
+        loggable(request, &content)
+    );
+    Ok(content)
+}
+
+/// `text` as it may appear in the log: only its length and hash in privacy mode.
+fn loggable(request: &FixRequest<'_>, text: &str) -> String {
+    if request.privacy {
+        redact::fingerprint(text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Reads the answer as the alternatives array or a single `GrammarResponse`.
+fn parse_reply(content: &str, alternatives: bool) -> Result<GrammarResponse, String> {
+    if !alternatives {
+        return parse::parse_json(content);
+    }
+    let alternatives = parse::parse_json::<AlternativesResponse>(content)?.alternatives;
+    let first = alternatives.first().ok_or("the answer has no alternatives")?;
+    Ok(GrammarResponse {
+        corrected: first.corrected.clone(),
+        edits: Vec::new(),
+        detected_language: None,
+        alternatives,
+    })
+}
+
+/// Fixes each chunk with at most `concurrency` requests in flight and joins the results with the
+/// original separators. A failed chunk keeps its original text and is reported in the returned
+/// errors; the whole fix only fails when no chunk succeeded, and counts as the provider being
+/// unavailable when every chunk failed that way.
+async fn fix_chunks(
+    request: &FixRequest<'_>,
+    chunks: &[Chunk<'_>],
+    concurrency: usize,
+    on_progress: &(dyn Fn(&str) + Send + Sync),
+) -> Result<(GrammarResponse, Vec<ChunkError>), AppError> {
+    let total = chunks.len();
+    let requests: Vec<_> = chunks.iter().map(|chunk| fix_chunk(request, *chunk)).collect();
+    let mut results = futures_util::stream::iter(requests).buffered(concurrency.max(1));
+
+    let mut parts: Vec<String> = Vec::with_capacity(total);
+    let mut edits = Vec::new();
+    let mut detected_language = None;
+    let mut errors = Vec::new();
+    let mut failures = Vec::new();
+    while let Some(result) = results.next().await {
+        let index = parts.len();
+        match result {
+            Ok(chunk_result) => {
+                parts.push(chunk_result.corrected);
+                edits.extend(chunk_result.edits);
+                // The first chunk that reports a language speaks for the whole text.
+                detected_language = detected_language.or(chunk_result.detected_language);
+            }
+            Err(e) => {
+                log::warn!("Chunk {} of {} failed: {}", index + 1, total, e);
+                let e = e.context(&format!("Chunk {} of {} failed: ", index + 1, total));
+                // Correction is serialized as is, so the message is scrubbed here, not by AppError.
+                errors.push(ChunkError {
+                    index,
+                    message: redact::redact(e.message()),
+                });
+                parts.push(chunks[index].text.to_string());
+                failures.push(e);
+            }
+        }
+        on_progress(&join_chunks(chunks, &parts));
+    }
+
+    // Text that is only whitespace has no chunk to attempt and comes back unchanged.
+    let attempted = chunks.iter().filter(|c| !c.is_blank()).count();
+    if attempted > 0 && errors.len() == attempted {
+        // Only report the provider as unavailable (and try the next one) if every chunk said so.
+        let failure = failures
+            .iter()
+            .find(|e| !e.is_unavailable())
+            .or(failures.first())
+            .cloned()
+            .expect("at least one chunk failed");
+        return Err(failure.context(&format!("All {} chunks failed. ", attempted)));
+    }
+    let result = GrammarResponse {
+        corrected: join_chunks(chunks, &parts),
+        edits,
+        detected_language,
+        alternatives: Vec::new(),
+    };
+    Ok((result, errors))
+}
+
+async fn fix_chunk(request: &FixRequest<'_>, chunk: Chunk<'_>) -> Result<GrammarResponse, AppError> {
+    if chunk.is_blank() {
+        return Ok(GrammarResponse {
+            corrected: chunk.text.to_string(),
+            edits: Vec::new(),
+            detected_language: None,
+            alternatives: Vec::new(),
+        });
+    }
+    let mut result = fix_text(request, chunk.text, &|_| {}).await?;
+    // Models tend to drop leading whitespace; keep the chunk's own.
+    let leading = &chunk.text[..chunk.text.len() - chunk.text.trim_start().len()];
+    result.corrected = format!("{}{}", leading, result.corrected.trim());
+    Ok(result)
+}
+
+/// Joins corrected chunk texts with the original separators; chunks without a result yet keep
+/// their original text.
+fn join_chunks(chunks: &[Chunk<'_>], corrected: &[String]) -> String {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let text = corrected.get(i).map_or(chunk.text, String::as_str);
+            format!("{}{}", text, chunk.separator)
+        })
+        .collect()
+}
+
+/// Test the AI connection using current config (API key, model, effective base).
+/// Sends the provider's probe request and returns Ok(()) if the API responds successfully.
+pub async fn test_connection(config: &AppSettings) -> Result<(), AppError> {
+    let provider = provider_for(config);
+    let client = local::client_builder(config, provider.base_url())
+        .await?
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(AppError::from_transport)?;
+
+    let retry = RetryPolicy::from_settings(config);
+    let body = send(provider.as_ref(), &retry, || provider.probe_request(&client)).await?;
+    provider.check_probe(&body).map_err(AppError::invalid_response)
+}
+
+/// How many per-model detail requests run at once while listing models.
+const MODEL_DETAIL_CONCURRENCY: usize = 4;
+/// Upper bound on listing pages, in case an endpoint keeps handing out the same token.
+const MAX_MODEL_PAGES: usize = 50;
+
+/// Lists the models the configured provider offers, sorted by ID. Capabilities come from the
+/// listing itself and, where the provider needs one, a detail request per model; a failed detail
+/// request leaves that model's capabilities unknown.
+pub async fn list_models(config: &AppSettings) -> Result<Vec<ModelInfo>, AppError> {
+    let provider = provider_for(config);
+    let client = local::client_builder(config, provider.base_url())
+        .await?
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(AppError::from_transport)?;
+    let retry = RetryPolicy::from_settings(config);
+    let mut models = Vec::new();
+    let mut page: Option<String> = None;
+    for _ in 0..MAX_MODEL_PAGES {
+        let body = send(provider.as_ref(), &retry, || provider.models_request(&client, page.as_deref())).await?;
+        models.extend(provider.parse_models(&body).map_err(AppError::invalid_response)?);
+        page = provider.next_models_page(&body);
+        if page.is_none() {
+            break;
+        }
+    }
+    if page.is_some() {
+        log::warn!("Stopped listing models after {} pages", MAX_MODEL_PAGES);
+    }
+
+    let requests: Vec<_> = models
+        .into_iter()
+        .map(|model| add_model_detail(provider.as_ref(), &client, &retry, model))
+        .collect();
+    let mut models: Vec<ModelInfo> = futures_util::stream::iter(requests)
+        .buffered(MODEL_DETAIL_CONCURRENCY)
+        .collect()
+        .await;
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+async fn add_model_detail(
+    provider: &dyn Provider,
+    client: &Client,
+    retry: &RetryPolicy,
+    mut model: ModelInfo,
+) -> ModelInfo {
+    let Some(request) = provider.model_detail_request(client, &model.id) else {
+        return model;
+    };
+    let detail = send(provider, retry, || {
+        request.try_clone().expect("detail requests have no streaming body")
+    })
+    .await
+    .and_then(|body| provider.parse_model_detail(&body, &mut model).map_err(AppError::invalid_response));
+    if let Err(e) = detail {
+        log::debug!("No details for model {}: {}", model.id, e);
+    }
+    model
+}
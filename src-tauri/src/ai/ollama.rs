@@ -0,0 +1,249 @@
+use super::format::OutputFormat;
+use super::{ChatInput, ModelInfo, Provider};
+use crate::settings::GenerationParams;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Native Ollama client (`/api/chat`). Unlike the OpenAI-compatible `/v1` shim it accepts
+/// `keep_alive`, model `options` such as `num_ctx`, and a JSON schema as `format`.
+/// Model discovery uses `/api/tags` and `/api/show`, which report capabilities.
+pub struct Ollama {
+    /// Server root without the `/v1` suffix, e.g. `http://localhost:11434`.
+    root: String,
+    api_key: String,
+    model: String,
+    keep_alive: Option<serde_json::Value>,
+    options: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Ollama {
+    /// `base` may be the server root or its `/v1` URL, so existing Ollama settings keep working.
+    /// `num_ctx` and the generation params override the same keys in `options`.
+    pub fn new(
+        base: String,
+        api_key: String,
+        model: String,
+        params: &GenerationParams,
+        keep_alive: &str,
+        num_ctx: Option<u32>,
+        mut options: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        let trimmed = base.trim_end_matches('/');
+        let root = trimmed.strip_suffix("/v1").unwrap_or(trimmed).to_string();
+        let mut set = |key: &str, value: Option<serde_json::Value>| {
+            if let Some(value) = value {
+                options.insert(key.to_string(), value);
+            }
+        };
+        set("num_ctx", num_ctx.map(Into::into));
+        set("temperature", params.temperature.map(Into::into));
+        set("top_p", params.top_p.map(Into::into));
+        set("num_predict", params.max_tokens.map(Into::into));
+        set("seed", params.seed.map(Into::into));
+        set("stop", (!params.stop.is_empty()).then(|| params.stop.clone().into()));
+        set("presence_penalty", params.presence_penalty.map(Into::into));
+        set("frequency_penalty", params.frequency_penalty.map(Into::into));
+        Ollama {
+            root,
+            api_key,
+            model,
+            keep_alive: parse_keep_alive(keep_alive),
+            options,
+        }
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.root, path)
+    }
+}
+
+/// `keep_alive` is either a duration string ("10m", "1h") or a number of seconds (-1 keeps the
+/// model loaded forever, 0 unloads it right away). Empty means Ollama's default.
+fn parse_keep_alive(value: &str) -> Option<serde_json::Value> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(match value.parse::<i64>() {
+        Ok(seconds) => seconds.into(),
+        Err(_) => value.into(),
+    })
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    /// Ollama streams by default, so this is always sent.
+    stream: bool,
+    /// `"json"` or a JSON schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    options: &'a serde_json::Map<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a serde_json::Value>,
+}
+
+/// Both the full response and each streamed line: `{"message":{"content":"..."},"done":false}`.
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: Option<Message>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    capabilities: Vec<String>,
+    /// Architecture-prefixed keys, e.g. `"llama.context_length": 131072`.
+    #[serde(default)]
+    model_info: HashMap<String, serde_json::Value>,
+}
+
+impl Provider for Ollama {
+    fn endpoint(&self) -> String {
+        format!("{} {}", self.root, self.model)
+    }
+
+    fn base_url(&self) -> &str {
+        &self.root
+    }
+
+    fn chat_request(&self, client: &Client, input: &ChatInput) -> RequestBuilder {
+        let req = ChatRequest {
+            model: &self.model,
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: &input.system,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: &input.user,
+                },
+            ],
+            stream: input.stream,
+            format: match input.format {
+                OutputFormat::JsonSchema => Some(input.schema.clone()),
+                OutputFormat::JsonObject => Some("json".into()),
+                OutputFormat::Text => None,
+            },
+            options: &self.options,
+            keep_alive: self.keep_alive.as_ref(),
+        };
+        client.post(self.api_url("chat")).json(&req)
+    }
+
+    /// Plain Ollama needs no key; one is only sent for servers behind an authenticating proxy.
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        if self.api_key.is_empty() || self.api_key == "ollama" {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+
+    fn parse_response(&self, body: &str) -> Result<String, String> {
+        let resp: ChatResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        if let Some(error) = resp.error {
+            return Err(format!("Ollama error: {}", error));
+        }
+        resp.message
+            .map(|m| m.content)
+            .ok_or_else(|| "Ollama returned no message".to_string())
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Option<String>, String> {
+        let resp: ChatResponse = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if let Some(error) = resp.error {
+            return Err(format!("Ollama error in stream: {}", error));
+        }
+        Ok(resp.message.map(|m| m.content))
+    }
+
+    /// Ollama answers 404 `{"error":"model \"x\" not found, try pulling it first"}` for models
+    /// that are not installed.
+    fn describe_error(&self, status: StatusCode, body: &str) -> Option<String> {
+        let error = serde_json::from_str::<ErrorResponse>(body).ok()?.error;
+        if status == StatusCode::NOT_FOUND && error.contains("not found") {
+            return Some(format!(
+                "Model '{}' is not installed in Ollama. Run `ollama pull {}` or choose another model.",
+                self.model, self.model
+            ));
+        }
+        Some(format!("Ollama error {}: {}", status, error))
+    }
+
+    /// Ollama reports a schema it cannot use as an error about the `format` field.
+    fn rejects_format(&self, body: &str) -> bool {
+        serde_json::from_str::<ErrorResponse>(body).is_ok_and(|e| e.error.contains("format"))
+    }
+
+    fn models_request(&self, client: &Client, _page: Option<&str>) -> RequestBuilder {
+        client.get(self.api_url("tags"))
+    }
+
+    fn parse_models(&self, body: &str) -> Result<Vec<ModelInfo>, String> {
+        let tags: TagsResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        Ok(tags
+            .models
+            .into_iter()
+            .map(|m| ModelInfo {
+                // Ollama constrains output to JSON for every model.
+                json_mode: Some(true),
+                ..ModelInfo::new(m.name)
+            })
+            .collect())
+    }
+
+    fn model_detail_request(&self, client: &Client, id: &str) -> Option<RequestBuilder> {
+        Some(
+            client
+                .post(self.api_url("show"))
+                .json(&serde_json::json!({ "model": id })),
+        )
+    }
+
+    fn parse_model_detail(&self, body: &str, model: &mut ModelInfo) -> Result<(), String> {
+        let show: ShowResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        if !show.capabilities.is_empty() {
+            model.vision = Some(show.capabilities.iter().any(|c| c == "vision"));
+        }
+        model.context_length = show
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+        Ok(())
+    }
+}
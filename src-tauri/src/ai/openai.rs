@@ -0,0 +1,270 @@
+use super::format::OutputFormat;
+use super::{ChatInput, ModelInfo, Provider};
+use crate::settings::GenerationParams;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+pub const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Name of the output token cap. OpenAI deprecated `max_tokens` (reasoning models reject it),
+/// but most compatible servers only know `max_tokens`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MaxTokensField {
+    MaxTokens,
+    MaxCompletionTokens,
+}
+
+/// Speaks the OpenAI `/chat/completions` protocol. Used for OpenAI itself and for any
+/// compatible endpoint (Ollama's /v1 shim, LM Studio, custom gateways).
+pub struct OpenAiCompatible {
+    base: String,
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+    max_tokens_field: MaxTokensField,
+}
+
+impl OpenAiCompatible {
+    pub fn new(
+        base: String,
+        api_key: String,
+        model: String,
+        params: GenerationParams,
+        max_tokens_field: MaxTokensField,
+    ) -> Self {
+        OpenAiCompatible {
+            base,
+            api_key,
+            model,
+            params,
+            max_tokens_field,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base.trim_end_matches('/'), path)
+    }
+}
+
+// OpenAI-compatible request (minimal fields)
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+/// Request JSON output: "json_schema" with our schema, or plain "json_object" mode.
+#[derive(Serialize)]
+struct ResponseFormat<'a> {
+    #[serde(rename = "type")]
+    type_: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<JsonSchemaFormat<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonSchemaFormat<'a> {
+    name: &'static str,
+    strict: bool,
+    schema: &'a serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    /// Enforces JSON output; supported by OpenAI and Ollama /v1/chat/completions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    stop: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+}
+
+// OpenAI-compatible response (minimal fields)
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: Option<String>,
+}
+
+// Streamed chunk: `data: {"choices":[{"delta":{"content":"..."}}]}`
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+/// Only `id` is standard. Some compatible servers add capability fields: OpenRouter reports
+/// `context_length`, `architecture.input_modalities` and `supported_parameters`, vLLM `max_model_len`.
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+    name: Option<String>,
+    context_length: Option<u64>,
+    max_model_len: Option<u64>,
+    architecture: Option<Architecture>,
+    supported_parameters: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Architecture {
+    #[serde(default)]
+    input_modalities: Vec<String>,
+}
+
+impl Provider for OpenAiCompatible {
+    fn endpoint(&self) -> String {
+        format!("{} {}", self.base, self.model)
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    fn chat_request(&self, client: &Client, input: &ChatInput) -> RequestBuilder {
+        let req = ChatRequest {
+            model: &self.model,
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: &input.system,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: &input.user,
+                },
+            ],
+            response_format: match input.format {
+                OutputFormat::JsonSchema => Some(ResponseFormat {
+                    type_: "json_schema",
+                    json_schema: Some(JsonSchemaFormat {
+                        name: "response",
+                        strict: true,
+                        schema: &input.schema,
+                    }),
+                }),
+                OutputFormat::JsonObject => Some(ResponseFormat {
+                    type_: "json_object",
+                    json_schema: None,
+                }),
+                OutputFormat::Text => None,
+            },
+            stream: input.stream.then_some(true),
+            temperature: self.params.temperature,
+            top_p: self.params.top_p,
+            max_tokens: self
+                .params
+                .max_tokens
+                .filter(|_| self.max_tokens_field == MaxTokensField::MaxTokens),
+            max_completion_tokens: self
+                .params
+                .max_tokens
+                .filter(|_| self.max_tokens_field == MaxTokensField::MaxCompletionTokens),
+            seed: self.params.seed,
+            stop: &self.params.stop,
+            presence_penalty: self.params.presence_penalty,
+            frequency_penalty: self.params.frequency_penalty,
+        };
+        client.post(self.url("chat/completions")).json(&req)
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+
+    fn parse_response(&self, body: &str) -> Result<String, String> {
+        let chat: ChatResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        chat.choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .ok_or_else(|| "API returned no choices or content".to_string())
+    }
+
+    fn parse_stream_event(&self, data: &str) -> Result<Option<String>, String> {
+        let chunk: StreamChunk = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if let Some(error) = chunk.error {
+            return Err(format!("API error in stream: {}", error));
+        }
+        Ok(chunk.choices.into_iter().next().and_then(|c| c.delta.content))
+    }
+
+    /// Endpoints without structured output name the field they rejected.
+    fn rejects_format(&self, body: &str) -> bool {
+        ["response_format", "json_schema"].iter().any(|field| body.contains(field))
+    }
+
+    fn models_request(&self, client: &Client, _page: Option<&str>) -> RequestBuilder {
+        client.get(self.url("models"))
+    }
+
+    fn parse_models(&self, body: &str) -> Result<Vec<ModelInfo>, String> {
+        let models: ModelsResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        Ok(models
+            .data
+            .into_iter()
+            .map(|m| ModelInfo {
+                display_name: m.name,
+                context_length: m.context_length.or(m.max_model_len),
+                json_mode: m
+                    .supported_parameters
+                    .map(|p| p.iter().any(|p| p == "response_format" || p == "structured_outputs")),
+                vision: m.architecture.map(|a| a.input_modalities.iter().any(|i| i == "image")),
+                ..ModelInfo::new(m.id)
+            })
+            .collect())
+    }
+
+    fn check_probe(&self, body: &str) -> Result<(), String> {
+        let chat: ChatResponse = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        if chat.choices.is_empty() {
+            return Err("API returned no choices".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use serde::de::DeserializeOwned;
+
+/// Tags some models wrap their reasoning in before the answer.
+const REASONING_TAGS: &[&str] = &["think", "thinking", "reasoning"];
+
+/// Removes `<think>...</think>` style blocks. An unterminated block (output cut off while
+/// reasoning) drops everything after it; a lone closing tag (some templates open the block in the
+/// prompt) drops everything before it.
+pub fn strip_reasoning(content: &str) -> String {
+    let mut out = content.to_string();
+    for tag in REASONING_TAGS {
+        let open = format!("<{}>", tag);
+        let close = format!("</{}>", tag);
+        while let Some(start) = out.find(&open) {
+            match out[start..].find(&close) {
+                Some(len) => out.replace_range(start..start + len + close.len(), ""),
+                None => out.truncate(start),
+            }
+        }
+        if let Some(end) = out.find(&close) {
+            out.replace_range(..end + close.len(), "");
+        }
+    }
+    out
+}
+
+/// The balanced `{...}` starting at byte `start`, skipping braces inside strings.
+fn balanced_object_at(text: &str, start: usize) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the first balanced JSON object in `content` that deserializes as `T`, ignoring
+/// reasoning blocks, preambles, code fences and trailing text. Unknown keys are ignored as long
+/// as `T` does not deny them.
+pub fn parse_json<T: DeserializeOwned>(content: &str) -> Result<T, String> {
+    let content = strip_reasoning(content);
+    let mut first_error = None;
+    for (start, _) in content.match_indices('{') {
+        let Some(object) = balanced_object_at(&content, start) else {
+            continue;
+        };
+        match serde_json::from_str::<T>(object) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                first_error.get_or_insert(e.to_string());
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "no complete JSON object in the answer".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Answer {
+        corrected: String,
+    }
+
+    fn answer(text: &str) -> Answer {
+        Answer {
+            corrected: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_fenced_json() {
+        let content = "Here you go:\n```json\n{\"corrected\": \"Hello.\"}\n```";
+        assert_eq!(parse_json::<Answer>(content), Ok(answer("Hello.")));
+    }
+
+    #[test]
+    fn ignores_trailing_prose() {
+        let content = "{\"corrected\": \"Hi there.\"}\n\nI fixed the capitalization {and} punctuation.";
+        assert_eq!(parse_json::<Answer>(content), Ok(answer("Hi there.")));
+    }
+
+    #[test]
+    fn keeps_escaped_quotes_and_braces_in_strings() {
+        let content = r#"{"corrected": "She said \"hi\" } and {left\\"}"#;
+        assert_eq!(parse_json::<Answer>(content), Ok(answer(r#"She said "hi" } and {left\"#)));
+    }
+
+    #[test]
+    fn skips_objects_of_the_wrong_shape() {
+        let content = r#"Example: {"note": "ignore me"} Answer: {"corrected": "Done."}"#;
+        assert_eq!(parse_json::<Answer>(content), Ok(answer("Done.")));
+    }
+
+    #[test]
+    fn skips_reasoning_blocks() {
+        let content = "<think>{\"corrected\": \"draft\"}</think>{\"corrected\": \"final\"}";
+        assert_eq!(parse_json::<Answer>(content), Ok(answer("final")));
+        assert_eq!(strip_reasoning("setup</think>answer"), "answer");
+        assert_eq!(strip_reasoning("answer<thinking>cut off"), "answer");
+    }
+
+    #[test]
+    fn reports_missing_object() {
+        assert!(parse_json::<Answer>("{\"corrected\": \"unterminated").is_err());
+        assert!(parse_json::<Answer>("no json here").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use super::Redaction;
+use super::pii::{self, Detector};
+use crate::error::AppError;
+use crate::settings::AppSettings;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Spans models tend to "fix" although they must stay verbatim, with the plural name of their
+/// kind for messages. Earlier patterns win when matches overlap at the same position, so code
+/// comes before the URLs it may contain.
+const BUILT_IN_PATTERNS: &[(&str, &str)] = &[
+    // Fenced and inline code
+    ("code spans", r"(?s)```.*?```"),
+    ("code spans", r"`[^`\n]+`"),
+    // URLs, without trailing punctuation
+    ("URLs", r#"\b(?:https?|ftp)://[^\s<>]*[^\s<>.,;:!?'")\]]"#),
+    ("URLs", r#"\bwww\.[^\s<>]*[^\s<>.,;:!?'")\]]"#),
+    ("email addresses", r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.[A-Za-z]{2,}\b"),
+    ("mentions", r"\B@\w+(?:[.-]\w+)*"),
+    // {{placeholders}}, {placeholders} and ${variables}
+    ("placeholders", r"\{\{[^{}\n]+\}\}"),
+    ("placeholders", r"\$?\{[\w.:-]+\}"),
+    // Ticket IDs (ABC-123) and issue references (#123)
+    ("ticket IDs", r"\b[A-Z][A-Z0-9]+-\d+\b"),
+    ("issue references", r"\B#\d+\b"),
+];
+
+/// Kind of the spans matched by `protected_patterns`.
+const USER_PATTERN_KIND: &str = "matches of your protected patterns";
+
+fn built_in() -> &'static [(&'static str, Regex)] {
+    static PATTERNS: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        BUILT_IN_PATTERNS
+            .iter()
+            .map(|&(kind, p)| (kind, Regex::new(p).expect("built-in patterns are valid")))
+            .collect()
+    })
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>, AppError> {
+    patterns
+        .iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| Regex::new(p).map_err(|e| AppError::InvalidInput(format!("Invalid protected pattern '{}': {}", p, e))))
+        .collect()
+}
+
+/// Checks that every user pattern is a valid regex, so a typo is reported when settings are saved.
+pub fn check_patterns(patterns: &[String]) -> Result<(), AppError> {
+    compile(patterns).map(|_| ())
+}
+
+/// Text with its protected spans swapped for opaque tokens, and what to put back.
+pub struct Masked {
+    /// The text as sent to the model.
+    pub text: String,
+    /// Spans in order of appearance.
+    spans: Vec<Span>,
+    /// Counts of redacted personal data and secrets, by kind.
+    pub redactions: Vec<Redaction>,
+}
+
+struct Span {
+    token: String,
+    original: String,
+    kind: Kind,
+}
+
+/// What a span holds. Messages name only the kind, never the original, as they end up in the log.
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Text that must stay verbatim, with the plural name of its kind ("URLs").
+    Protected(&'static str),
+    /// Personal data or a secret that was not sent.
+    Redacted(&'static Detector),
+}
+
+/// Replaces every protected span in `text` with a token such as `__KEEP_0__`, and with
+/// `pii_redaction` on every piece of personal data or secret with one such as `__EMAIL_1__`.
+/// User patterns from `protected_patterns` come after the built-in ones. Redacted data wins
+/// over protected spans that overlap it, since protected spans are still sent.
+///
+/// Fails with `SensitiveData` when a detector listed in `pii_block` finds something, whether or
+/// not `pii_redaction` is on.
+pub fn mask(text: &str, config: &AppSettings) -> Result<Masked, AppError> {
+    if let Some((_, _, detector)) = pii::find(text, &config.pii_block).first() {
+        return Err(AppError::SensitiveData(format!(
+            "The text was not sent because it contains {}, which are blocked. Remove them, or stop blocking {} in Settings.",
+            detector.label, detector.label
+        )));
+    }
+    let found = if config.pii_redaction {
+        pii::find(text, &config.pii_detectors)
+    } else {
+        Vec::new()
+    };
+    let overlaps_found = |start: usize, end: usize| found.iter().any(|&(s, e, _)| start < e && s < end);
+
+    let user = if config.protect_spans {
+        compile(&config.protected_patterns)?
+    } else {
+        Vec::new()
+    };
+    let built_ins = if config.protect_spans { built_in() } else { &[] };
+    // (start, end, priority, kind); the leftmost, then longest, then earliest pattern wins.
+    let mut matches: Vec<(usize, usize, usize, Kind)> = built_ins
+        .iter()
+        .map(|(kind, re)| (*kind, re))
+        .chain(user.iter().map(|re| (USER_PATTERN_KIND, re)))
+        .enumerate()
+        .flat_map(|(i, (kind, re))| {
+            re.find_iter(text).map(move |m| (m.start(), m.end(), i + 1, Kind::Protected(kind)))
+        })
+        .filter(|&(start, end, _, _)| start < end && !overlaps_found(start, end))
+        .chain(found.iter().map(|&(start, end, d)| (start, end, 0, Kind::Redacted(d))))
+        .collect();
+    matches.sort_by_key(|&(start, end, i, _)| (start, std::cmp::Reverse(end), i));
+
+    // A suffix for the tags that does not already occur in the text, so tokens cannot collide.
+    let tags: Vec<&str> = std::iter::once("KEEP").chain(found.iter().map(|(_, _, d)| d.tag)).collect();
+    let suffix = (0..)
+        .map(|n| if n == 0 { String::new() } else { n.to_string() })
+        .find(|suffix| !tags.iter().any(|tag| text.contains(&format!("__{}{}_", tag, suffix))))
+        .expect("some suffix is unused");
+
+    let mut masked = String::with_capacity(text.len());
+    let mut spans = Vec::new();
+    let mut redactions: Vec<Redaction> = Vec::new();
+    let mut pos = 0;
+    for (start, end, _, kind) in matches {
+        if start < pos {
+            continue;
+        }
+        let tag = match kind {
+            Kind::Protected(_) => "KEEP",
+            Kind::Redacted(detector) => detector.tag,
+        };
+        let token = format!("__{}{}_{}__", tag, suffix, spans.len());
+        masked.push_str(&text[pos..start]);
+        masked.push_str(&token);
+        spans.push(Span {
+            token,
+            original: text[start..end].to_string(),
+            kind,
+        });
+        if let Kind::Redacted(detector) = kind {
+            match redactions.iter_mut().find(|r| r.kind == detector.id) {
+                Some(redaction) => redaction.count += 1,
+                None => redactions.push(Redaction {
+                    kind: detector.id.to_string(),
+                    label: detector.label.to_string(),
+                    count: 1,
+                }),
+            }
+        }
+        pos = end;
+    }
+    masked.push_str(&text[pos..]);
+    Ok(Masked {
+        text: masked,
+        spans,
+        redactions,
+    })
+}
+
+impl Masked {
+    /// A token to show the model as an example, if anything was masked.
+    pub fn example_token(&self) -> Option<&str> {
+        self.spans.first().map(|span| span.token.as_str())
+    }
+
+    /// Puts the original spans back wherever their tokens appear, without checking them. For
+    /// partial output and free-form fields such as edit reasons.
+    pub fn restore_lenient(&self, text: &str) -> String {
+        self.spans
+            .iter()
+            .fold(text.to_string(), |text, span| text.replace(&span.token, &span.original))
+    }
+
+    /// Puts the original spans back, failing when the model dropped or repeated a token.
+    pub fn restore(&self, text: &str) -> Result<String, AppError> {
+        for span in &self.spans {
+            // Spans are named by their kind only, as error messages end up in the log.
+            let (what, setting) = match span.kind {
+                Kind::Protected(kind) => (format!("one of the protected {}", kind), "protected spans"),
+                Kind::Redacted(detector) => (format!("the placeholder for one of the redacted {}", detector.label), "redaction"),
+            };
+            match text.matches(span.token.as_str()).count() {
+                1 => {}
+                0 => {
+                    return Err(AppError::invalid_response(format!(
+                        "The answer dropped {}, so it was discarded. Try again, or turn off {} in Settings.",
+                        what, setting
+                    )));
+                }
+                n => {
+                    return Err(AppError::invalid_response(format!(
+                        "The answer repeated {} {} times, so it was discarded. Try again.",
+                        what, n
+                    )));
+                }
+            }
+        }
+        Ok(self.restore_lenient(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AppSettings {
+        AppSettings {
+            protect_spans: true,
+            pii_redaction: false,
+            ..AppSettings::default()
+        }
+    }
+
+    #[test]
+    fn restores_every_token() {
+        let text = "Ask @anna about `cargo fix` on https://example.com/docs.";
+        let masked = mask(text, &settings()).unwrap();
+        assert_eq!(masked.text, "Ask __KEEP_0__ about __KEEP_1__ on __KEEP_2__.");
+        assert_eq!(masked.restore(&masked.text).unwrap(), text);
+    }
+
+    #[test]
+    fn dropped_token_fails_without_the_original() {
+        let masked = mask("Read https://example.com/secret-path first.", &settings()).unwrap();
+        let error = masked.restore("Read it first.").unwrap_err();
+        assert!(matches!(error, AppError::InvalidResponse { .. }));
+        assert!(error.message().contains("dropped one of the protected URLs"));
+        assert!(!error.message().contains("example.com"));
+    }
+
+    #[test]
+    fn repeated_token_fails() {
+        let masked = mask("Ping @anna today.", &settings()).unwrap();
+        let error = masked.restore("Ping __KEEP_0__ and __KEEP_0__ today.").unwrap_err();
+        assert!(matches!(error, AppError::InvalidResponse { .. }));
+        assert!(error.message().contains("repeated one of the protected mentions 2 times"));
+        assert!(!error.message().contains("anna"));
+    }
+
+    #[test]
+    fn redacted_data_wins_over_protected_spans() {
+        let config = AppSettings {
+            pii_redaction: true,
+            pii_detectors: vec!["email".to_string()],
+            ..settings()
+        };
+        // The built-in email pattern matches too, but the address must not be sent.
+        let masked = mask("Mail anna@example.com about #42.", &config).unwrap();
+        assert_eq!(masked.text, "Mail __EMAIL_0__ about __KEEP_1__.");
+        assert_eq!(masked.redactions.len(), 1);
+        assert_eq!(masked.redactions[0].kind, "email");
+        assert_eq!(masked.redactions[0].count, 1);
+        let error = masked.restore("Mail them about __KEEP_1__.").unwrap_err();
+        assert!(error.message().contains("redacted email addresses"));
+    }
+
+    #[test]
+    fn tokens_do_not_collide_with_the_text() {
+        let text = "The literal __KEEP_0__ stays, and so does `code`.";
+        let masked = mask(text, &settings()).unwrap();
+        assert_eq!(masked.text, "The literal __KEEP_0__ stays, and so does __KEEP1_0__.");
+        assert_eq!(masked.restore(&masked.text).unwrap(), text);
+    }
+
+    #[test]
+    fn nothing_is_masked_when_protection_is_off() {
+        let config = AppSettings {
+            protect_spans: false,
+            ..settings()
+        };
+        let masked = mask("See https://example.com.", &config).unwrap();
+        assert_eq!(masked.text, "See https://example.com.");
+        assert!(masked.example_token().is_none());
+    }
+}
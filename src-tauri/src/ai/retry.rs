@@ -0,0 +1,146 @@
+use crate::error::is_connection_error;
+use crate::settings::AppSettings;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How often and how long to retry a provider call that failed transiently.
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries.
+    pub max_attempts: u32,
+    /// Upper bound on the time spent sleeping between attempts.
+    pub max_total_wait: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_settings(config: &AppSettings) -> Self {
+        RetryPolicy {
+            max_attempts: config.retry_max_attempts.max(1),
+            max_total_wait: Duration::from_secs(config.retry_max_wait_secs),
+        }
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and all of base * 2^(attempt-1).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = BASE_DELAY.saturating_mul(1u32 << (attempt - 1).min(16)).min(MAX_DELAY);
+        let half = exp / 2;
+        let jitter = RandomState::new().hash_one(attempt) % (half.as_millis() as u64 + 1);
+        half + Duration::from_millis(jitter)
+    }
+}
+
+/// Sends the request produced by `build`, retrying 429 and 5xx responses and dropped connections.
+/// Server hints (`Retry-After`, `x-ratelimit-reset-*`) take precedence over the backoff. Once the
+/// attempts or the wait budget run out, the last response or error is returned as is.
+pub async fn send_with_retry(
+    policy: &RetryPolicy,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, reqwest::Error> {
+    let mut waited = Duration::ZERO;
+    let mut attempt = 1;
+    loop {
+        let result = build().send().await;
+        let delay = match &result {
+            Ok(response) if is_retryable_status(response.status()) => {
+                server_delay(response.status(), response.headers()).unwrap_or_else(|| policy.backoff(attempt))
+            }
+            // Timeouts are not retried: the request may still be running on the server and
+            // another full wait rarely helps.
+            Err(e) if is_connection_error(e) => policy.backoff(attempt),
+            _ => return result,
+        };
+        if attempt >= policy.max_attempts || waited + delay > policy.max_total_wait {
+            return result;
+        }
+        match &result {
+            Ok(response) => log::info!(
+                "{} on attempt {}, retrying in {:?}",
+                response.status(),
+                attempt,
+                delay
+            ),
+            Err(e) => log::info!("{} on attempt {}, retrying in {:?}", e, attempt, delay),
+        }
+        tokio::time::sleep(delay).await;
+        waited += delay;
+        attempt += 1;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The wait the server asked for. `Retry-After` wins; on 429 we otherwise use the
+/// `x-ratelimit-reset-<bucket>` headers of exhausted buckets (`x-ratelimit-remaining-<bucket>: 0`),
+/// or the soonest reset if none is marked exhausted.
+pub fn server_delay(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    let header_delay = |name: &str| headers.get(name)?.to_str().ok().and_then(parse_delay);
+    if let Some(delay) = header_delay("retry-after") {
+        return Some(delay);
+    }
+    if status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let resets: Vec<(&str, Duration)> = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let bucket = name.as_str().strip_prefix("x-ratelimit-reset-")?;
+            Some((bucket, value.to_str().ok().and_then(parse_delay)?))
+        })
+        .collect();
+    let exhausted = resets
+        .iter()
+        .filter(|(bucket, _)| {
+            headers
+                .get(format!("x-ratelimit-remaining-{}", bucket))
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.trim() == "0")
+        })
+        .map(|&(_, delay)| delay)
+        .max();
+    exhausted.or_else(|| resets.iter().map(|&(_, delay)| delay).min())
+}
+
+/// Parses a reset hint: plain seconds ("2", "1.5"), a Unix timestamp, or an OpenAI-style
+/// duration ("1s", "250ms", "6m0s", "1h2m3.5s"). HTTP-date values are not supported.
+fn parse_delay(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        if !secs.is_finite() || secs < 0.0 {
+            return None;
+        }
+        // Values this large are absolute Unix timestamps rather than relative seconds.
+        if secs > 1_000_000_000.0 {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs_f64();
+            return Some(Duration::from_secs_f64((secs - now).max(0.0)));
+        }
+        return Some(Duration::from_secs_f64(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let number: f64 = rest[..split].parse().ok()?;
+        let unit_end = rest[split..]
+            .find(|c: char| c.is_ascii_digit())
+            .map_or(rest.len(), |i| split + i);
+        let unit = match &rest[split..unit_end] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        total += Duration::from_secs_f64(number * unit);
+        rest = &rest[unit_end..];
+    }
+    Some(total)
+}
@@ -0,0 +1,93 @@
+use super::Provider;
+use crate::error::AppError;
+use reqwest::Response;
+
+/// Reads a server-sent-events body, feeding each `data:` payload to the provider and passing the
+/// accumulated text to `on_text` after every delta. Returns the full text once the stream ends.
+/// Bare JSON lines are accepted too, so newline-delimited JSON streams work the same way.
+pub async fn read_event_stream(
+    provider: &dyn Provider,
+    mut response: Response,
+    on_text: &(dyn Fn(&str) + Send + Sync),
+) -> Result<String, AppError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut text = String::new();
+
+    while let Some(chunk) = response.chunk().await.map_err(AppError::from_transport)? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if handle_line(provider, line.trim_end(), &mut text)? {
+                on_text(&text);
+            }
+        }
+    }
+    let rest = String::from_utf8_lossy(&buffer).into_owned();
+    if handle_line(provider, rest.trim_end(), &mut text)? {
+        on_text(&text);
+    }
+    Ok(text)
+}
+
+/// Applies one line of the stream to `text`. Returns true if new text was appended. An error
+/// event from the provider fails the stream with `AppError::Api`.
+fn handle_line(provider: &dyn Provider, line: &str, text: &mut String) -> Result<bool, AppError> {
+    let payload = if let Some(data) = line.strip_prefix("data:") {
+        data.trim_start()
+    } else if line.starts_with('{') {
+        line
+    } else {
+        // SSE comments, `event:`/`id:` fields and blank separators carry no text.
+        return Ok(false);
+    };
+    if payload.is_empty() || payload == "[DONE]" {
+        return Ok(false);
+    }
+    let event = provider
+        .parse_stream_event(payload)
+        .map_err(|message| AppError::Api { status: None, message })?;
+    match event {
+        Some(delta) if !delta.is_empty() => {
+            text.push_str(&delta);
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Best-effort read of the `"corrected"` string from a JSON object that is still being streamed,
+/// e.g. `{"corrected": "Hello wor`. Returns None until the value has started.
+pub fn partial_corrected(content: &str) -> Option<String> {
+    let key = content.find("\"corrected\"")?;
+    let rest = content[key + "\"corrected\"".len()..].trim_start();
+    let rest = rest.strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+
+    let mut out = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('b') => out.push('\u{8}'),
+                Some('f') => out.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(ch) if hex.len() == 4 => out.push(ch),
+                        // Incomplete escape or half of a surrogate pair; wait for more input.
+                        _ => break,
+                    }
+                }
+                Some(other) => out.push(other),
+                None => break,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,115 @@
+use crate::ai::Correction;
+use crate::error::AppError;
+use crate::settings::AppSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const CACHE_FILENAME: &str = "response_cache.json";
+
+/// Serializes reads and writes of the cache file between concurrent fixes.
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    correction: Correction,
+    /// Unix seconds when the answer was stored; entries older than the TTL are dropped.
+    created_at: u64,
+    /// Unix seconds of the last hit, for LRU eviction.
+    last_used: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    entries: HashMap<String, CacheEntry>,
+}
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app.path().app_data_dir().map_err(|e| AppError::Storage(e.to_string()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| AppError::Storage(e.to_string()))?;
+    Ok(dir.join(CACHE_FILENAME))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A missing or unreadable cache file is treated as empty.
+fn load(path: &PathBuf) -> CacheFile {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(path: &PathBuf, cache: &CacheFile) -> Result<(), AppError> {
+    let json = serde_json::to_string(cache).map_err(|e| AppError::Storage(e.to_string()))?;
+    std::fs::write(path, json).map_err(|e| AppError::Storage(e.to_string()))
+}
+
+/// Drops expired entries, then the least recently used ones until at most `max_entries` remain.
+fn evict(cache: &mut CacheFile, config: &AppSettings, now: u64) {
+    let ttl = config.cache_ttl_hours.saturating_mul(3600);
+    cache.entries.retain(|_, e| now.saturating_sub(e.created_at) < ttl);
+    if cache.entries.len() > config.cache_max_entries {
+        let mut by_age: Vec<(u64, String)> = cache
+            .entries
+            .iter()
+            .map(|(key, e)| (e.last_used, key.clone()))
+            .collect();
+        by_age.sort();
+        let excess = cache.entries.len() - config.cache_max_entries;
+        for (_, key) in by_age.into_iter().take(excess) {
+            cache.entries.remove(&key);
+        }
+    }
+}
+
+/// Returns the stored answer for `key`, marked as cached, if it exists and has not expired.
+pub fn lookup(app: &AppHandle, key: &str, config: &AppSettings) -> Option<Correction> {
+    let _guard = CACHE_LOCK.lock().ok()?;
+    let path = cache_path(app).ok()?;
+    let mut cache = load(&path);
+    let now = now();
+    evict(&mut cache, config, now);
+    let entry = cache.entries.get_mut(key)?;
+    entry.last_used = now;
+    let mut correction = entry.correction.clone();
+    correction.cached = true;
+    let _ = save(&path, &cache);
+    Some(correction)
+}
+
+/// Stores a fresh answer under `key`, evicting old entries to stay within the size cap.
+pub fn store(app: &AppHandle, key: &str, correction: &Correction, config: &AppSettings) -> Result<(), AppError> {
+    let _guard = CACHE_LOCK.lock().map_err(|e| AppError::Storage(e.to_string()))?;
+    let path = cache_path(app)?;
+    let mut cache = load(&path);
+    let now = now();
+    cache.entries.insert(
+        key.to_string(),
+        CacheEntry {
+            correction: correction.clone(),
+            created_at: now,
+            last_used: now,
+        },
+    );
+    evict(&mut cache, config, now);
+    save(&path, &cache)
+}
+
+/// Deletes every cached answer.
+pub fn clear(app: &AppHandle) -> Result<(), AppError> {
+    let _guard = CACHE_LOCK.lock().map_err(|e| AppError::Storage(e.to_string()))?;
+    let path = cache_path(app)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::Storage(e.to_string())),
+        _ => Ok(()),
+    }
+}
@@ -1,25 +1,139 @@
 use crate::ai;
+use crate::cache;
+use crate::error::AppError;
+use crate::logging;
+use crate::requests::RequestRegistry;
 use crate::settings;
+use tauri::{Emitter, Manager, State};
 use tauri_plugin_global_shortcut::GlobalShortcutExt;
 
+/// Runs the default "fix" action. `request_id` lets the popup cancel it with `cancel_fix_command`.
 #[tauri::command]
-pub async fn fix_grammar_command(app: tauri::AppHandle, text: String) -> Result<ai::Correction, String> {
+pub async fn fix_grammar_command(
+    app: tauri::AppHandle,
+    requests: State<'_, RequestRegistry>,
+    text: String,
+    request_id: Option<u64>,
+) -> Result<ai::Correction, AppError> {
+    let options = ai::actions::ActionOptions::default();
+    run_action(&app, &requests, ai::actions::DEFAULT_ACTION, &options, text, request_id).await
+}
+
+/// Runs a named action ("fix", "formal", "translate", ...) on `text`. `options` carries the
+/// languages for "translate".
+#[tauri::command]
+pub async fn run_action_command(
+    app: tauri::AppHandle,
+    requests: State<'_, RequestRegistry>,
+    action: String,
+    text: String,
+    options: Option<ai::actions::ActionOptions>,
+    request_id: Option<u64>,
+) -> Result<ai::Correction, AppError> {
+    let options = options.unwrap_or_default();
+    run_action(&app, &requests, &action, &options, text, request_id).await
+}
+
+/// The actions the popup can offer, in display order.
+#[tauri::command]
+pub fn list_actions_command() -> &'static [ai::actions::Action] {
+    ai::actions::ACTIONS
+}
+
+async fn run_action(
+    app: &tauri::AppHandle,
+    requests: &RequestRegistry,
+    action: &str,
+    options: &ai::actions::ActionOptions,
+    text: String,
+    request_id: Option<u64>,
+) -> Result<ai::Correction, AppError> {
     if text.trim().is_empty() {
-        return Err("Please enter text to fix.".to_string());
+        return Err(AppError::InvalidInput("Please enter text to fix.".to_string()));
+    }
+    let action = ai::actions::find(action)?;
+    let cfg = settings::load_settings(app)?;
+    let cache_key = cfg.cache_enabled.then(|| ai::cache_key(&text, &cfg, action, options));
+    if let Some(hit) = cache_key.as_deref().and_then(|key| cache::lookup(app, key, &cfg)) {
+        return Ok(hit);
+    }
+
+    let on_progress = |partial: &str| {
+        let _ = app.emit_to("main", "fix-progress", partial);
+    };
+    let correction = requests
+        .run(request_id, ai::run_action_with_config(action, options, text, &cfg, &on_progress))
+        .await?;
+    // Partial results (some chunks failed) are not cached so the next run retries them, and
+    // fallback answers are not cached so the main provider is used again once it is back.
+    let stored = cache_key
+        .filter(|_| correction.chunk_errors.is_empty() && !correction.fallback)
+        .map(|key| cache::store(app, &key, &correction, &cfg));
+    if let Some(Err(e)) = stored {
+        log::warn!("Failed to cache response: {}", e);
+    }
+    Ok(correction)
+}
+
+/// Puts the chosen suggestion in place of the original selection: hides the popup so focus goes
+/// back to the app the text came from, then pastes `text` there.
+#[tauri::command]
+pub async fn commit_candidate_command(app: tauri::AppHandle, text: String) -> Result<(), AppError> {
+    if let Some(window) = app.get_webview_window("main") {
+        // Failing to hide only means the paste may land in the popup; try anyway.
+        let _ = window.hide();
+    }
+    replace_selection(&app, &text).await
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+async fn replace_selection(app: &tauri::AppHandle, text: &str) -> Result<(), AppError> {
+    use crate::accessibility::{AccessibilityService, PlatformAccessibility};
+    // Give the previous window time to take focus back before pasting into it.
+    tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+    PlatformAccessibility::new(app).replace_selected_text(text)
+}
+
+/// Without a way to paste into other apps, the text is left on the clipboard.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+async fn replace_selection(app: &tauri::AppHandle, text: &str) -> Result<(), AppError> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| AppError::ClipboardUnavailable(e.to_string()))
+}
+
+#[tauri::command]
+pub fn clear_cache_command(app: tauri::AppHandle) -> Result<(), AppError> {
+    cache::clear(&app)
+}
+
+/// Cancels the fix started with `request_id`, or every running fix if it is omitted.
+#[tauri::command]
+pub fn cancel_fix_command(requests: State<'_, RequestRegistry>, request_id: Option<u64>) -> bool {
+    match request_id {
+        Some(id) => requests.cancel(id),
+        None => {
+            requests.cancel_all();
+            true
+        }
     }
-    let cfg = settings::load_settings(&app).unwrap_or_default();
-    ai::fix_grammar_with_config(text, &cfg).await
 }
 
 #[tauri::command]
-pub fn get_settings_command(app: tauri::AppHandle) -> Result<settings::AppSettings, String> {
+pub fn get_settings_command(app: tauri::AppHandle) -> Result<settings::AppSettings, AppError> {
     settings::load_settings(&app)
 }
 
 const DEFAULT_HOTKEY: &str = "Ctrl+Shift+Space";
 
 #[tauri::command]
-pub fn save_settings_command(app: tauri::AppHandle, settings: settings::AppSettings) -> Result<(), String> {
+pub fn save_settings_command(app: tauri::AppHandle, settings: settings::AppSettings) -> Result<(), AppError> {
+    ai::check_patterns(&settings.protected_patterns)?;
+    logging::parse_filter(&settings.log_filter)?;
+    ai::pii::check_detectors(&settings.pii_detectors)?;
+    ai::pii::check_detectors(&settings.pii_block)?;
+    ai::check_allowlist(&settings.local_allowlist)?;
     let old_settings = settings::load_settings(&app).unwrap_or_default();
     let old_hotkey = old_settings.hotkey.trim();
     let old_hotkey: &str = if old_hotkey.is_empty() {
@@ -42,25 +156,40 @@ pub fn save_settings_command(app: tauri::AppHandle, settings: settings::AppSetti
             let _ = app.global_shortcut().unregister(old_hotkey);
             if let Err(e) = app.global_shortcut().register(new_hotkey.as_str()) {
                 let _ = app.global_shortcut().register(old_hotkey);
-                return Err(format!(
+                return Err(AppError::InvalidInput(format!(
                     "Failed to register shortcut '{}'. It may be in use by another application. ({})",
                     new_hotkey, e
-                ));
+                )));
             }
         }
     }
 
     let mut to_save = settings;
     to_save.hotkey = new_hotkey;
-    settings::save_settings(&app, &to_save)
+    settings::save_settings(&app, &to_save)?;
+    logging::set_filter(&to_save.log_filter)
 }
 
 #[tauri::command]
-pub async fn test_ai_connection_command(settings: settings::AppSettings) -> Result<(), String> {
+pub async fn test_ai_connection_command(settings: settings::AppSettings) -> Result<(), AppError> {
     ai::test_connection(&settings).await
 }
 
+/// Lists the models offered by the provider in `settings` (the unsaved form values).
+#[tauri::command]
+pub async fn list_models_command(settings: settings::AppSettings) -> Result<Vec<ai::ModelInfo>, AppError> {
+    ai::list_models(&settings).await
+}
+
+/// Writes a message from the webview to the log file, at info level unless `level` says otherwise.
+#[tauri::command]
+pub fn debug_log(message: String, level: Option<String>) {
+    let level = level.and_then(|l| l.parse().ok()).unwrap_or(log::Level::Info);
+    log::log!(target: "frontend", level, "{}", message);
+}
+
+/// The last `limit` log entries (200 by default), oldest first.
 #[tauri::command]
-pub fn debug_log(message: String) {
-    println!("[Frontend Debug] {}", message);
+pub fn get_recent_logs_command(limit: Option<usize>) -> Result<Vec<logging::LogEntry>, AppError> {
+    logging::recent(limit.unwrap_or(200))
 }
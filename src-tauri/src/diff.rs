@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Beyond this many edits the Myers search stops and the rest is reported as one replacement,
+/// which keeps memory bounded for completely rewritten text.
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanKind {
+    Insert,
+    Delete,
+    Replace,
+}
+
+/// A range in one of the two strings, as byte offsets (for Rust) and UTF-16 offsets (for the webview).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+    pub start_utf16: usize,
+    pub end_utf16: usize,
+}
+
+/// One difference between the original and corrected text. Inserts have an empty `original`
+/// range and deletes an empty `corrected` range, positioned where the change happens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiffSpan {
+    pub kind: SpanKind,
+    pub original: TextRange,
+    pub corrected: TextRange,
+    /// Character-level spans inside a word-level replacement. Empty for other spans.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chars: Vec<DiffSpan>,
+}
+
+/// Word-level diff between `original` and `corrected`, with character-level detail for replacements.
+/// Words, whitespace runs and single punctuation marks are compared as separate tokens.
+pub fn diff_words(original: &str, corrected: &str) -> Vec<DiffSpan> {
+    let a = tokenize(original);
+    let b = tokenize(corrected);
+    let a_text: Vec<&str> = a.iter().map(|r| &original[r.clone()]).collect();
+    let b_text: Vec<&str> = b.iter().map(|r| &corrected[r.clone()]).collect();
+    let a_utf16 = Utf16Index::new(original);
+    let b_utf16 = Utf16Index::new(corrected);
+
+    hunks(&a_text, &b_text)
+        .into_iter()
+        .map(|(ta, tb)| {
+            let ra = token_bytes(&a, ta, original.len());
+            let rb = token_bytes(&b, tb, corrected.len());
+            let kind = span_kind(&ra, &rb);
+            let chars = if kind == SpanKind::Replace {
+                diff_chars(original, corrected, ra.clone(), rb.clone(), &a_utf16, &b_utf16)
+            } else {
+                Vec::new()
+            };
+            DiffSpan {
+                kind,
+                original: a_utf16.range(ra),
+                corrected: b_utf16.range(rb),
+                chars,
+            }
+        })
+        .collect()
+}
+
+fn diff_chars(
+    original: &str,
+    corrected: &str,
+    ra: Range<usize>,
+    rb: Range<usize>,
+    a_utf16: &Utf16Index,
+    b_utf16: &Utf16Index,
+) -> Vec<DiffSpan> {
+    let a: Vec<(usize, char)> = original[ra.clone()].char_indices().map(|(i, c)| (ra.start + i, c)).collect();
+    let b: Vec<(usize, char)> = corrected[rb.clone()].char_indices().map(|(i, c)| (rb.start + i, c)).collect();
+    let a_chars: Vec<char> = a.iter().map(|&(_, c)| c).collect();
+    let b_chars: Vec<char> = b.iter().map(|&(_, c)| c).collect();
+    let char_bytes = |chars: &[(usize, char)], r: Range<usize>, end: usize| {
+        let start = chars.get(r.start).map_or(end, |&(i, _)| i);
+        let stop = chars.get(r.end).map_or(end, |&(i, _)| i);
+        start..stop
+    };
+
+    hunks(&a_chars, &b_chars)
+        .into_iter()
+        .map(|(ca, cb)| {
+            let ba = char_bytes(&a, ca, ra.end);
+            let bb = char_bytes(&b, cb, rb.end);
+            DiffSpan {
+                kind: span_kind(&ba, &bb),
+                original: a_utf16.range(ba),
+                corrected: b_utf16.range(bb),
+                chars: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn span_kind(a: &Range<usize>, b: &Range<usize>) -> SpanKind {
+    match (a.is_empty(), b.is_empty()) {
+        (true, _) => SpanKind::Insert,
+        (_, true) => SpanKind::Delete,
+        _ => SpanKind::Replace,
+    }
+}
+
+/// Splits text into byte ranges of words (alphanumerics and apostrophes), whitespace runs and
+/// individual punctuation characters.
+fn tokenize(text: &str) -> Vec<Range<usize>> {
+    #[derive(PartialEq, Clone, Copy)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '\'' || c == '\u{2019}' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut current: Option<(usize, Class)> = None;
+    for (i, c) in text.char_indices() {
+        let cls = class(c);
+        match current {
+            Some((_, prev)) if prev == cls && cls != Class::Other => {}
+            Some((start, _)) => {
+                tokens.push(start..i);
+                current = Some((i, cls));
+            }
+            None => current = Some((i, cls)),
+        }
+    }
+    if let Some((start, _)) = current {
+        tokens.push(start..text.len());
+    }
+    tokens
+}
+
+/// Byte range covered by a run of tokens; an empty run maps to the position where it would start.
+fn token_bytes(tokens: &[Range<usize>], run: Range<usize>, text_len: usize) -> Range<usize> {
+    let start = tokens.get(run.start).map_or(text_len, |t| t.start);
+    if run.is_empty() {
+        return start..start;
+    }
+    start..tokens[run.end - 1].end
+}
+
+/// Maps byte offsets at char boundaries to UTF-16 offsets.
+struct Utf16Index {
+    /// (byte offset, utf16 offset) for every char start, plus the end of the string.
+    points: Vec<(usize, usize)>,
+}
+
+impl Utf16Index {
+    fn new(text: &str) -> Self {
+        let mut points = Vec::with_capacity(text.len() + 1);
+        let mut utf16 = 0;
+        for (i, c) in text.char_indices() {
+            points.push((i, utf16));
+            utf16 += c.len_utf16();
+        }
+        points.push((text.len(), utf16));
+        Utf16Index { points }
+    }
+
+    fn offset(&self, byte: usize) -> usize {
+        match self.points.binary_search_by_key(&byte, |&(b, _)| b) {
+            Ok(i) => self.points[i].1,
+            Err(i) => self.points[i.saturating_sub(1)].1,
+        }
+    }
+
+    fn range(&self, bytes: Range<usize>) -> TextRange {
+        TextRange {
+            start: bytes.start,
+            end: bytes.end,
+            start_utf16: self.offset(bytes.start),
+            end_utf16: self.offset(bytes.end),
+        }
+    }
+}
+
+/// Runs of differing elements as (range in `a`, range in `b`), in order.
+fn hunks<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(Range<usize>, Range<usize>)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut result = Vec::new();
+    let mut pending: Option<(Range<usize>, Range<usize>)> = None;
+    let mut i = 0;
+    let mut j = 0;
+    for step in edit_script(a_mid, b_mid) {
+        match step {
+            Step::Keep => {
+                result.extend(pending.take());
+                i += 1;
+                j += 1;
+            }
+            Step::Delete => {
+                let hunk = pending.get_or_insert((i..i, j..j));
+                hunk.0.end = i + 1;
+                i += 1;
+            }
+            Step::Insert => {
+                let hunk = pending.get_or_insert((i..i, j..j));
+                hunk.1.end = j + 1;
+                j += 1;
+            }
+        }
+    }
+    result.extend(pending.take());
+
+    result
+        .into_iter()
+        .map(|(ra, rb)| (ra.start + prefix..ra.end + prefix, rb.start + prefix..rb.end + prefix))
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Myers' O(ND) shortest edit script turning `a` into `b`.
+fn edit_script<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Step> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds v[-d..=d] as it was before round d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut final_d = None;
+
+    'search: for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                final_d = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    let Some(final_d) = final_d else {
+        // Too many edits: report everything as one replacement.
+        return std::iter::repeat_n(Step::Delete, a.len())
+            .chain(std::iter::repeat_n(Step::Insert, b.len()))
+            .collect();
+    };
+
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=final_d).rev() {
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev = &trace[d as usize];
+            let at = |k: isize| prev[(k + d) as usize];
+            let k = x - y;
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            script.push(Step::Keep);
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                script.push(Step::Insert);
+            } else {
+                x -= 1;
+                script.push(Step::Delete);
+            }
+        }
+    }
+    script.reverse();
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, end: usize, start_utf16: usize, end_utf16: usize) -> TextRange {
+        TextRange {
+            start,
+            end,
+            start_utf16,
+            end_utf16,
+        }
+    }
+
+    #[test]
+    fn identical_text_has_no_spans() {
+        assert!(diff_words("The same sentence.", "The same sentence.").is_empty());
+        assert!(diff_words("", "").is_empty());
+    }
+
+    #[test]
+    fn insert_at_start() {
+        let spans = diff_words("world", "Hello world");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Insert);
+        assert_eq!(spans[0].original, range(0, 0, 0, 0));
+        assert_eq!(spans[0].corrected, range(0, 6, 0, 6));
+    }
+
+    #[test]
+    fn insert_at_end() {
+        let spans = diff_words("Hello", "Hello world");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Insert);
+        assert_eq!(spans[0].original, range(5, 5, 5, 5));
+        assert_eq!(spans[0].corrected, range(5, 11, 5, 11));
+    }
+
+    #[test]
+    fn delete_at_start() {
+        let spans = diff_words("Well, fine", "fine");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Delete);
+        assert_eq!(spans[0].original, range(0, 6, 0, 6));
+        assert_eq!(spans[0].corrected, range(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn delete_at_end() {
+        let spans = diff_words("fine, well", "fine");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Delete);
+        assert_eq!(spans[0].original, range(4, 10, 4, 10));
+        assert_eq!(spans[0].corrected, range(4, 4, 4, 4));
+    }
+
+    #[test]
+    fn replacement_after_non_bmp_characters_uses_utf16_offsets() {
+        // "😀" is 4 bytes and 2 UTF-16 units, "é" 2 bytes and 1 unit.
+        let spans = diff_words("😀 teh café", "😀 the café");
+        assert_eq!(spans.len(), 1);
+        let span = &spans[0];
+        assert_eq!(span.kind, SpanKind::Replace);
+        assert_eq!(span.original, range(5, 8, 3, 6));
+        assert_eq!(span.corrected, range(5, 8, 3, 6));
+        // "teh" -> "the": the character-level detail stays inside the word.
+        assert!(!span.chars.is_empty());
+        for c in &span.chars {
+            assert!(c.original.start >= 5 && c.original.end <= 8);
+            assert_eq!(c.original.start_utf16, c.original.start - 2);
+        }
+    }
+
+    #[test]
+    fn replaced_non_bmp_character() {
+        let spans = diff_words("a 😀 b", "a 🎉 b");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Replace);
+        assert_eq!(spans[0].original, range(2, 6, 2, 4));
+        assert_eq!(spans[0].corrected, range(2, 6, 2, 4));
+        let after = diff_words("😀😀 x", "😀😀 y");
+        assert_eq!(after[0].original, range(9, 10, 5, 6));
+    }
+
+    #[test]
+    fn too_many_edits_become_one_replacement() {
+        let a: Vec<u32> = (0..MAX_EDIT_DISTANCE as u32 + 10).collect();
+        let b: Vec<u32> = a.iter().map(|x| x + 100_000).collect();
+        let hunks = hunks(&a, &b);
+        assert_eq!(hunks, vec![(0..a.len(), 0..b.len())]);
+    }
+
+    #[test]
+    fn rewritten_text_past_the_limit_is_one_span() {
+        let original = "a ".repeat(MAX_EDIT_DISTANCE);
+        let corrected = "b ".repeat(MAX_EDIT_DISTANCE);
+        let spans = diff_words(&original, &corrected);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, SpanKind::Replace);
+        assert_eq!(spans[0].original, range(0, original.len() - 1, 0, original.len() - 1));
+    }
+}
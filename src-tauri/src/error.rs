@@ -0,0 +1,215 @@
+use crate::redact;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::time::Duration;
+
+/// Error returned by the backend. Serialized for the frontend as
+/// `{code, message, retryable, retry_after_secs, detail}`: `code` is stable and meant for branching,
+/// `message` is shown to the user as is, after secrets are scrubbed from it.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// The provider could not be reached (connection refused or reset, DNS, TLS).
+    Network(String),
+    /// The provider did not answer in time.
+    Timeout(String),
+    /// The provider rejected the API key (401/403).
+    Auth(String),
+    /// Too many requests (429). `retry_after` is the server's hint, if it sent one.
+    RateLimited { message: String, retry_after: Option<Duration> },
+    /// The model does not exist or is not installed (a 404 that names the model).
+    ModelNotFound(String),
+    /// Any other error answer from the provider; `status` is None for errors inside a stream.
+    Api { status: Option<u16>, message: String },
+    /// The answer could not be used. `raw` is the model's output when it could not be parsed.
+    InvalidResponse { message: String, raw: Option<String> },
+    /// The request itself is unusable: empty text, unknown action, invalid pattern or shortcut.
+    InvalidInput(String),
+    /// The text contains data the redaction settings say must never be sent, e.g. a private key.
+    SensitiveData(String),
+    /// Local-only mode refused a host that is not on this machine or in the allowlist.
+    HostBlocked(String),
+    /// settings.json exists but cannot be read.
+    SettingsCorrupt(String),
+    /// Reading or writing the app's files failed.
+    Storage(String),
+    ClipboardUnavailable(String),
+    Cancelled,
+}
+
+impl AppError {
+    pub fn invalid_response(message: impl Into<String>) -> Self {
+        AppError::InvalidResponse {
+            message: message.into(),
+            raw: None,
+        }
+    }
+
+    /// Classifies a reqwest error that happened before or while reading a response. Only timeouts
+    /// and failed or dropped connections mean the provider is unreachable.
+    pub fn from_transport(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            AppError::Timeout(format!("The provider did not answer in time: {}", error))
+        } else if is_connection_error(&error) {
+            AppError::Network(format!("Could not reach the provider: {}", error))
+        } else if error.is_builder() {
+            AppError::InvalidInput(format!("Could not build the request, check the API base URL: {}", error))
+        } else if error.is_decode() {
+            AppError::invalid_response(format!("Could not read the provider's answer: {}", error))
+        } else {
+            AppError::Api {
+                status: error.status().map(|s| s.as_u16()),
+                message: format!("The request to the provider failed: {}", error),
+            }
+        }
+    }
+
+    /// Error for a non-success HTTP status. `message` is the provider's description of it. A 404
+    /// is only a missing model when the description names the model; otherwise the URL is wrong.
+    pub fn from_status(status: reqwest::StatusCode, retry_after: Option<Duration>, message: String) -> Self {
+        match status.as_u16() {
+            401 | 403 => AppError::Auth(message),
+            404 if message.to_lowercase().contains("model") => AppError::ModelNotFound(message),
+            404 => AppError::Api {
+                status: Some(404),
+                message: format!("{} Check the API base URL.", message.trim_end()),
+            },
+            429 => AppError::RateLimited { message, retry_after },
+            code => AppError::Api {
+                status: Some(code),
+                message,
+            },
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "network",
+            AppError::Timeout(_) => "timeout",
+            AppError::Auth(_) => "auth",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::ModelNotFound(_) => "model_not_found",
+            AppError::Api { .. } => "api",
+            AppError::InvalidResponse { .. } => "invalid_response",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::SensitiveData(_) => "sensitive_data",
+            AppError::HostBlocked(_) => "host_blocked",
+            AppError::SettingsCorrupt(_) => "settings_corrupt",
+            AppError::Storage(_) => "storage",
+            AppError::ClipboardUnavailable(_) => "clipboard_unavailable",
+            AppError::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Network(message)
+            | AppError::Timeout(message)
+            | AppError::Auth(message)
+            | AppError::RateLimited { message, .. }
+            | AppError::ModelNotFound(message)
+            | AppError::Api { message, .. }
+            | AppError::InvalidResponse { message, .. }
+            | AppError::InvalidInput(message)
+            | AppError::SensitiveData(message)
+            | AppError::HostBlocked(message)
+            | AppError::SettingsCorrupt(message)
+            | AppError::Storage(message)
+            | AppError::ClipboardUnavailable(message) => message,
+            AppError::Cancelled => "Request cancelled.",
+        }
+    }
+
+    /// Whether trying the same thing again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Network(_)
+            | AppError::Timeout(_)
+            | AppError::RateLimited { .. }
+            | AppError::InvalidResponse { .. } => true,
+            AppError::Api { status, .. } => status.is_none_or(|s| s >= 500),
+            _ => false,
+        }
+    }
+
+    /// Whether the provider is down rather than refusing this request: the next provider in the
+    /// fallback chain should be tried.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            AppError::Network(_) | AppError::Timeout(_) => true,
+            AppError::Api { status, .. } => status.is_some_and(|s| s >= 500),
+            _ => false,
+        }
+    }
+
+    /// Prefixes the message, e.g. to name the chunk or provider it came from.
+    pub fn context(self, prefix: &str) -> Self {
+        let with = |message: String| format!("{}{}", prefix, message);
+        match self {
+            AppError::Network(m) => AppError::Network(with(m)),
+            AppError::Timeout(m) => AppError::Timeout(with(m)),
+            AppError::Auth(m) => AppError::Auth(with(m)),
+            AppError::RateLimited { message, retry_after } => AppError::RateLimited {
+                message: with(message),
+                retry_after,
+            },
+            AppError::ModelNotFound(m) => AppError::ModelNotFound(with(m)),
+            AppError::Api { status, message } => AppError::Api {
+                status,
+                message: with(message),
+            },
+            AppError::InvalidResponse { message, raw } => AppError::InvalidResponse {
+                message: with(message),
+                raw,
+            },
+            AppError::InvalidInput(m) => AppError::InvalidInput(with(m)),
+            AppError::SensitiveData(m) => AppError::SensitiveData(with(m)),
+            AppError::HostBlocked(m) => AppError::HostBlocked(with(m)),
+            AppError::SettingsCorrupt(m) => AppError::SettingsCorrupt(with(m)),
+            AppError::Storage(m) => AppError::Storage(with(m)),
+            AppError::ClipboardUnavailable(m) => AppError::ClipboardUnavailable(with(m)),
+            AppError::Cancelled => AppError::Cancelled,
+        }
+    }
+}
+
+/// Connection refused, reset or aborted (or DNS/TLS failing) before a response arrived.
+pub fn is_connection_error(error: &reqwest::Error) -> bool {
+    if error.is_connect() {
+        return true;
+    }
+    let mut source = std::error::Error::source(error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind::*;
+            return matches!(io.kind(), ConnectionReset | ConnectionAborted | BrokenPipe | UnexpectedEof);
+        }
+        source = err.source();
+    }
+    false
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let retry_after_secs = match self {
+            AppError::RateLimited { retry_after, .. } => retry_after.map(|d| d.as_secs().max(1)),
+            _ => None,
+        };
+        let detail = match self {
+            AppError::InvalidResponse { raw, .. } => raw.as_deref().map(redact::redact),
+            _ => None,
+        };
+        let mut s = serializer.serialize_struct("AppError", 5)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &redact::redact(self.message()))?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        s.serialize_field("retry_after_secs", &retry_after_secs)?;
+        s.serialize_field("detail", &detail)?;
+        s.end()
+    }
+}
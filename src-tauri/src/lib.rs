@@ -1,6 +1,12 @@
 mod accessibility;
 mod ai;
+mod cache;
 mod commands;
+mod diff;
+mod error;
+mod logging;
+mod redact;
+mod requests;
 mod settings;
 
 use tauri::{AppHandle, Manager, Emitter};
@@ -17,6 +23,7 @@ use windows::core::PCWSTR;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(requests::RequestRegistry::default())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(
@@ -32,17 +39,25 @@ pub fn run() {
                 .build(),
         )
         .setup(|app| {
+            let settings = settings::load_settings(app.handle());
+            let log_filter = settings.as_ref().map_or("info", |s| s.log_filter.as_str());
+            if let Err(e) = logging::init(app.handle(), log_filter) {
+                eprintln!("Failed to open the log file: {}", e);
+            }
+            if let Err(e) = &settings {
+                log::error!("{}", e);
+            }
             #[cfg(desktop)]
             {
                 let _ = app.handle().plugin(tauri_plugin_window_state::Builder::default().build());
             }
             #[cfg(desktop)]
             {
-                let settings = settings::load_settings(app.handle()).unwrap_or_default();
+                let settings = settings.unwrap_or_default();
                 let shortcut_str = settings.hotkey.trim();
                 let shortcut_str: &str = if shortcut_str.is_empty() { "Ctrl+Shift+Space" } else { shortcut_str };
                 if let Err(e) = app.global_shortcut().register(shortcut_str) {
-                    eprintln!("Failed to register shortcut '{}': {}", shortcut_str, e);
+                    log::error!("Failed to register shortcut '{}': {}", shortcut_str, e);
                     #[cfg(target_os = "windows")]
                     unsafe {
                         use windows::core::w;
@@ -99,9 +114,16 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::fix_grammar_command,
+            commands::run_action_command,
+            commands::list_actions_command,
+            commands::commit_candidate_command,
+            commands::cancel_fix_command,
+            commands::clear_cache_command,
             commands::get_settings_command,
             commands::save_settings_command,
             commands::test_ai_connection_command,
+            commands::list_models_command,
+            commands::get_recent_logs_command,
             commands::debug_log
         ])
         .run(tauri::generate_context!())
@@ -137,7 +159,9 @@ fn open_settings_window(app: &AppHandle) {
 }
 
 /// Opens the main popup window and sets its text. Used by hotkey, tray "Open", and tray left/double-click.
+/// A fix still running for the previous text is cancelled.
 fn open_popup_window(app: &AppHandle, text: String) {
+    app.state::<requests::RequestRegistry>().cancel_all();
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.emit("set-text", text);
         let _ = window.unminimize();
@@ -151,7 +175,7 @@ fn get_text_for_popup(app: &AppHandle) -> String {
     PlatformAccessibility::new(app)
         .get_selected_text()
         .unwrap_or_else(|e| {
-            eprintln!("Failed to get selected text: {}", e);
+            log::warn!("Failed to get selected text: {}", e);
             String::new()
         })
 }
@@ -166,6 +190,6 @@ fn handle_shortcut(app: &AppHandle) {
     if let Err(e) = app.run_on_main_thread(move || {
         open_popup_window(&app_for_main, text);
     }) {
-        eprintln!("run_on_main_thread failed: {}", e);
+        log::error!("run_on_main_thread failed: {}", e);
     }
 }
@@ -0,0 +1,239 @@
+use crate::error::AppError;
+use crate::redact;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const LOG_FILENAME: &str = "help-me-write.log";
+/// The log file is rotated once it grows past this size.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Rotated files kept next to the current one; `help-me-write.1.log` is the newest.
+const KEEP_ROTATED: usize = 3;
+/// Upper bound for `recent`, so the settings window never receives whole log files.
+const MAX_RECENT: usize = 2000;
+/// Start of this crate's log targets. It is stripped, so filters and entries name modules as
+/// `ai::fallback` rather than `tauri_app_lib::ai::fallback`.
+const CRATE_PREFIX: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// One line of the log file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogEntry {
+    /// Unix milliseconds.
+    pub ts: u64,
+    pub level: String,
+    /// Module that logged it (`ai::retry`), or `frontend` for messages from the webview.
+    pub target: String,
+    pub message: String,
+}
+
+/// Which levels are written: a default plus `module=level` overrides, where the longest matching
+/// module wins.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        }
+    }
+}
+
+impl Filter {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module || target.strip_prefix(module.as_str()).is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+/// Parses a filter like `info,ai=debug,reqwest=warn`. A bare level sets the default (info if
+/// there is none).
+pub fn parse_filter(spec: &str) -> Result<Filter, AppError> {
+    let parse_level = |level: &str| {
+        LevelFilter::from_str(level.trim()).map_err(|_| {
+            AppError::InvalidInput(format!(
+                "Unknown log level '{}'. Use off, error, warn, info, debug or trace.",
+                level.trim()
+            ))
+        })
+    };
+    let mut filter = Filter::default();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('=') {
+            Some((module, level)) => {
+                let module = module.trim();
+                let module = module.strip_prefix(CRATE_PREFIX).unwrap_or(module);
+                filter.modules.push((module.to_string(), parse_level(level)?));
+            }
+            None => filter.default = parse_level(part)?,
+        }
+    }
+    Ok(filter)
+}
+
+struct LogFile {
+    file: Option<File>,
+    size: u64,
+}
+
+struct FileLogger {
+    dir: PathBuf,
+    filter: RwLock<Filter>,
+    out: Mutex<LogFile>,
+}
+
+fn log_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(LOG_FILENAME)
+    } else {
+        dir.join(LOG_FILENAME.replace(".log", &format!(".{}.log", index)))
+    }
+}
+
+fn open(dir: &Path) -> LogFile {
+    let path = log_path(dir, 0);
+    let file = OpenOptions::new().create(true).append(true).open(&path).ok();
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    LogFile { file, size }
+}
+
+fn short_target(target: &str) -> &str {
+    target.strip_prefix(CRATE_PREFIX).unwrap_or(target)
+}
+
+impl FileLogger {
+    /// Shifts `help-me-write.log` to `.1.log` (and older files up by one), then starts a new file.
+    fn rotate(&self, out: &mut LogFile) {
+        out.file = None;
+        let _ = std::fs::remove_file(log_path(&self.dir, KEEP_ROTATED));
+        for index in (0..KEEP_ROTATED).rev() {
+            let _ = std::fs::rename(log_path(&self.dir, index), log_path(&self.dir, index + 1));
+        }
+        *out = open(&self.dir);
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filter = self.filter.read().unwrap_or_else(|e| e.into_inner());
+        metadata.level() <= filter.level_for(short_target(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = LogEntry {
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            level: record.level().to_string(),
+            target: short_target(record.target()).to_string(),
+            message: redact::redact(&record.args().to_string()),
+        };
+        // Release builds have no console on Windows; the file is the only sink there.
+        #[cfg(debug_assertions)]
+        eprintln!("{:<5} {}: {}", entry.level, entry.target, entry.message);
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        if out.size + line.len() as u64 > MAX_FILE_BYTES && out.size > 0 {
+            self.rotate(&mut out);
+        }
+        if let Some(file) = out.file.as_mut()
+            && file.write_all(line.as_bytes()).is_ok()
+        {
+            out.size += line.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(file) = out.file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Starts writing the log file under the app log dir with the levels from `spec`. An invalid
+/// spec (e.g. a hand-edited settings file) falls back to `info` and is logged.
+pub fn init(app: &AppHandle, spec: &str) -> Result<(), AppError> {
+    let dir = app.path().app_log_dir().map_err(|e| AppError::Storage(e.to_string()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| AppError::Storage(e.to_string()))?;
+    let (filter, filter_error) = match parse_filter(spec) {
+        Ok(filter) => (filter, None),
+        Err(e) => (Filter::default(), Some(e)),
+    };
+    let max_level = filter.max_level();
+    let out = Mutex::new(open(&dir));
+    let logger = LOGGER.get_or_init(|| FileLogger {
+        dir,
+        filter: RwLock::new(filter),
+        out,
+    });
+    log::set_logger(logger).map_err(|e| AppError::Storage(e.to_string()))?;
+    log::set_max_level(max_level);
+    if let Some(e) = filter_error {
+        log::warn!("Ignoring the log filter from the settings: {}", e);
+    }
+    Ok(())
+}
+
+/// Applies a new filter (after the settings are saved).
+pub fn set_filter(spec: &str) -> Result<(), AppError> {
+    let filter = parse_filter(spec)?;
+    if let Some(logger) = LOGGER.get() {
+        log::set_max_level(filter.max_level());
+        *logger.filter.write().unwrap_or_else(|e| e.into_inner()) = filter;
+    }
+    Ok(())
+}
+
+/// The last `limit` entries, oldest first, reading into the rotated files when the current one
+/// is shorter. Lines that are not entries are skipped.
+pub fn recent(limit: usize) -> Result<Vec<LogEntry>, AppError> {
+    let Some(logger) = LOGGER.get() else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.min(MAX_RECENT);
+    logger.flush();
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for index in 0..=KEEP_ROTATED {
+        if entries.len() >= limit {
+            break;
+        }
+        let contents = match std::fs::read_to_string(log_path(&logger.dir, index)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(AppError::Storage(e.to_string())),
+        };
+        let older: Vec<LogEntry> = contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+        entries.splice(0..0, older);
+    }
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
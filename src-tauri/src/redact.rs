@@ -0,0 +1,60 @@
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Credentials that must not reach the log file or an error message, with what replaces them.
+/// The first group, when there is one, is kept so the line still says what was removed.
+const SECRET_PATTERNS: &[(&str, &str)] = &[
+    // Authorization: Bearer <token>
+    (r"(?i)\b(bearer\s+)[A-Za-z0-9._~+/=-]{8,}", "${1}[REDACTED]"),
+    // api_key=..., "x-api-key": "...", ?key=..., token: ...
+    (
+        r#"(?i)((?:\b|")(?:api[_-]?key|x-api-key|x-goog-api-key|access[_-]?token|refresh[_-]?token|client[_-]?secret|secret|password|token|key)"?\s*[:=]\s*"?)[A-Za-z0-9._~+/=-]{8,}"#,
+        "${1}[REDACTED]",
+    ),
+];
+
+/// Key formats of common providers: OpenAI/Anthropic, Google, GitHub, Slack, AWS, and JWTs.
+pub const KEY_FORMATS: &[&str] = &[
+    r"\bsk-[A-Za-z0-9_-]{16,}",
+    r"\bAIza[0-9A-Za-z_-]{35}",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+];
+
+fn secret_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        SECRET_PATTERNS
+            .iter()
+            .copied()
+            .chain(KEY_FORMATS.iter().map(|p| (*p, REDACTED)))
+            .map(|(p, replacement)| (Regex::new(p).expect("secret patterns are valid"), replacement))
+            .collect()
+    })
+}
+
+/// Scrubs bearer tokens, key/token/password values and provider API keys from `text`. Applied to
+/// every log line and every error sent to the frontend.
+pub fn redact(text: &str) -> String {
+    secret_patterns()
+        .iter()
+        .fold(text.to_string(), |text, (pattern, replacement)| {
+            pattern.replace_all(&text, *replacement).into_owned()
+        })
+}
+
+/// Describes text for the log without its content: length and a short hash, enough to tell
+/// whether two requests carried the same text.
+pub fn fingerprint(text: &str) -> String {
+    let hash: String = Sha256::digest(text.as_bytes())
+        .iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{} chars, sha256 {}", text.chars().count(), hash)
+}
@@ -0,0 +1,66 @@
+use crate::error::AppError;
+use futures_util::future::{AbortHandle, Abortable};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Fixes currently in flight, by request ID. Managed as Tauri state so commands and the popup
+/// opener can cancel them.
+#[derive(Default)]
+pub struct RequestRegistry {
+    /// Serial that tells runs sharing an ID apart; also the source of generated IDs.
+    counter: AtomicU64,
+    running: Mutex<HashMap<u64, (u64, AbortHandle)>>,
+}
+
+impl RequestRegistry {
+    /// Runs `future` under `id` (or a fresh ID if None) until it finishes or is cancelled.
+    /// Cancelling drops the future, which also aborts its HTTP requests, and returns
+    /// `AppError::Cancelled`.
+    pub async fn run<T>(
+        &self,
+        id: Option<u64>,
+        future: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        let serial = self.counter.fetch_add(1, Ordering::Relaxed);
+        // Generated IDs count down from the top so they do not collide with the popup's own.
+        let id = id.unwrap_or(u64::MAX - serial);
+        let (handle, registration) = AbortHandle::new_pair();
+        if let Ok(mut running) = self.running.lock() {
+            // Reusing an ID replaces (and cancels) the older request.
+            if let Some((_, previous)) = running.insert(id, (serial, handle)) {
+                previous.abort();
+            }
+        }
+        let result = Abortable::new(future, registration).await;
+        if let Ok(mut running) = self.running.lock() {
+            // Only remove our own entry; a newer request may have taken over the ID.
+            if running.get(&id).is_some_and(|(s, _)| *s == serial) {
+                running.remove(&id);
+            }
+        }
+        result.unwrap_or(Err(AppError::Cancelled))
+    }
+
+    /// Cancels one request. Returns false if it was not running.
+    pub fn cancel(&self, id: u64) -> bool {
+        let entry = self.running.lock().ok().and_then(|mut running| running.remove(&id));
+        match entry {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancels every request in flight, e.g. when new text replaces the popup contents.
+    pub fn cancel_all(&self) {
+        if let Ok(mut running) = self.running.lock() {
+            for (_, (_, handle)) in running.drain() {
+                handle.abort();
+            }
+        }
+    }
+}
@@ -1,11 +1,67 @@
-use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use serde::{Deserialize, Deserializer, Serialize};
 use tauri::{AppHandle, Manager};
 use std::path::PathBuf;
 
 const SETTINGS_FILENAME: &str = "settings.json";
 
-fn default_ai_provider() -> String {
-    "ollama".to_string()
+/// Which backend `ai` talks to. Stored lowercase ("openai", "gemini", ...) as before; read
+/// case-insensitively and trimmed, so older files with "OpenAI" or "Gemini " still match.
+/// Unknown values fall back to Custom.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    OpenAi,
+    Gemini,
+    Anthropic,
+    Ollama,
+    Custom,
+}
+
+impl<'de> Deserialize<'de> for ProviderKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(match value.trim().to_lowercase().as_str() {
+            "openai" => ProviderKind::OpenAi,
+            "gemini" => ProviderKind::Gemini,
+            "anthropic" => ProviderKind::Anthropic,
+            "ollama" => ProviderKind::Ollama,
+            _ => ProviderKind::Custom,
+        })
+    }
+}
+
+/// Sampling and length settings sent with every request. Each is optional and only sent when set;
+/// providers that lack a parameter ignore it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct GenerationParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    /// Output token cap (`max_tokens`, `max_completion_tokens`, `maxOutputTokens` or `num_predict`).
+    pub max_tokens: Option<u32>,
+    pub seed: Option<i64>,
+    pub stop: Vec<String>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+}
+
+/// A provider to fall back to when the ones before it are unreachable. Generation and Ollama
+/// settings are shared with the main provider.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FallbackProvider {
+    #[serde(default = "default_ai_provider")]
+    pub ai_provider: ProviderKind,
+    #[serde(default)]
+    pub api_base: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default)]
+    pub model: String,
+}
+
+fn default_ai_provider() -> ProviderKind {
+    ProviderKind::Ollama
 }
 
 fn default_api_base() -> String {
@@ -26,6 +82,70 @@ fn default_system_prompt() -> String {
 No explanation. Output nothing but this JSON."#.to_string()
 }
 
+fn default_chunk_size() -> usize {
+    4000
+}
+
+fn default_chunk_concurrency() -> usize {
+    3
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_max_wait_secs() -> u64 {
+    30
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+fn default_cache_ttl_hours() -> u64 {
+    24 * 7
+}
+
+fn default_cache_max_entries() -> usize {
+    500
+}
+
+fn default_alternatives() -> usize {
+    1
+}
+
+fn default_protect_spans() -> bool {
+    true
+}
+
+fn default_breaker_threshold() -> u32 {
+    3
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    120
+}
+
+fn default_pii_detectors() -> Vec<String> {
+    crate::ai::pii::detector_ids()
+}
+
+fn default_pii_block() -> Vec<String> {
+    vec!["private_key".to_string()]
+}
+
+fn default_privacy_mode() -> bool {
+    true
+}
+
+fn default_log_filter() -> String {
+    "info".to_string()
+}
+
+fn default_target_language() -> String {
+    "English".to_string()
+}
+
 fn default_hotkey() -> String {
     "Ctrl+Shift+Space".to_string()
 }
@@ -33,7 +153,7 @@ fn default_hotkey() -> String {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppSettings {
     #[serde(default = "default_ai_provider")]
-    pub ai_provider: String,
+    pub ai_provider: ProviderKind,
     #[serde(default = "default_api_base")]
     pub api_base: String,
     #[serde(default = "default_api_key")]
@@ -44,6 +164,91 @@ pub struct AppSettings {
     pub system_prompt: String,
     #[serde(default = "default_hotkey")]
     pub hotkey: String,
+    /// Stream the answer and show partial corrections in the popup while it arrives.
+    #[serde(default)]
+    pub stream_responses: bool,
+    /// Ask the model to list each edit with a category and reason.
+    #[serde(default)]
+    pub explain_edits: bool,
+    /// Selections longer than this many characters are split at paragraph/sentence boundaries.
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    /// How many chunk requests may run at once.
+    #[serde(default = "default_chunk_concurrency")]
+    pub chunk_concurrency: usize,
+    /// Attempts per request (including the first) when the provider is rate limited, errors with 5xx
+    /// or drops the connection.
+    #[serde(default = "default_retry_max_attempts")]
+    pub retry_max_attempts: u32,
+    /// Longest total time to spend waiting between retries of one request.
+    #[serde(default = "default_retry_max_wait_secs")]
+    pub retry_max_wait_secs: u64,
+    /// Reuse earlier answers for the same text and settings instead of sending a new request.
+    #[serde(default = "default_cache_enabled")]
+    pub cache_enabled: bool,
+    /// Cached answers older than this are discarded.
+    #[serde(default = "default_cache_ttl_hours")]
+    pub cache_ttl_hours: u64,
+    /// Size cap of the cache; the least recently used answers are evicted beyond it.
+    #[serde(default = "default_cache_max_entries")]
+    pub cache_max_entries: usize,
+    /// How long Ollama keeps the model loaded after a request ("10m", "1h", or seconds; -1 = forever).
+    /// Empty uses Ollama's default.
+    #[serde(default)]
+    pub ollama_keep_alive: String,
+    /// Context window for Ollama. None uses the model's default.
+    #[serde(default)]
+    pub ollama_num_ctx: Option<u32>,
+    /// Extra Ollama model options (e.g. `{"num_gpu": 20}`), passed through as `options`.
+    #[serde(default)]
+    pub ollama_options: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub generation: GenerationParams,
+    /// Language the translate action writes in unless the popup picks another.
+    #[serde(default = "default_target_language")]
+    pub default_target_language: String,
+    /// How many labeled alternatives to ask for; 1 gives a single answer.
+    #[serde(default = "default_alternatives")]
+    pub alternatives: usize,
+    /// Tried in order when the provider above fails to connect, times out or answers 5xx.
+    #[serde(default)]
+    pub fallback_providers: Vec<FallbackProvider>,
+    /// Failures in a row after which a provider is skipped for the cool-down; 0 never skips.
+    #[serde(default = "default_breaker_threshold")]
+    pub breaker_threshold: u32,
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+    /// Swap code, URLs, mentions, placeholders, emails and ticket IDs for tokens before sending,
+    /// so the model cannot change them.
+    #[serde(default = "default_protect_spans")]
+    pub protect_spans: bool,
+    /// Extra regexes for spans to protect, on top of the built-in ones.
+    #[serde(default)]
+    pub protected_patterns: Vec<String>,
+    /// Replace personal data and secrets with placeholders before sending, and put them back in
+    /// the answer.
+    #[serde(default)]
+    pub pii_redaction: bool,
+    /// Detectors used for redaction: email, phone, iban, token, private_key.
+    #[serde(default = "default_pii_detectors")]
+    pub pii_detectors: Vec<String>,
+    /// Detectors whose findings stop the request instead of being redacted. Checked even when
+    /// `pii_redaction` is off.
+    #[serde(default = "default_pii_block")]
+    pub pii_block: Vec<String>,
+    /// Levels written to the log file: a default and `module=level` overrides, e.g. `info,ai=debug`.
+    #[serde(default = "default_log_filter")]
+    pub log_filter: String,
+    /// Refuse providers whose host does not resolve to this machine or an address in
+    /// `local_allowlist`.
+    #[serde(default)]
+    pub local_only: bool,
+    /// Addresses and ranges (e.g. `192.168.1.20`, `10.0.0.0/8`) allowed in local-only mode.
+    #[serde(default)]
+    pub local_allowlist: Vec<String>,
+    /// Log only the length, hash and timing of requests and answers, never the text itself.
+    #[serde(default = "default_privacy_mode")]
+    pub privacy_mode: bool,
 }
 
 impl Default for AppSettings {
@@ -55,27 +260,62 @@ impl Default for AppSettings {
             model: default_model(),
             system_prompt: default_system_prompt(),
             hotkey: default_hotkey(),
+            stream_responses: false,
+            explain_edits: false,
+            chunk_size: default_chunk_size(),
+            chunk_concurrency: default_chunk_concurrency(),
+            retry_max_attempts: default_retry_max_attempts(),
+            retry_max_wait_secs: default_retry_max_wait_secs(),
+            cache_enabled: default_cache_enabled(),
+            cache_ttl_hours: default_cache_ttl_hours(),
+            cache_max_entries: default_cache_max_entries(),
+            ollama_keep_alive: String::new(),
+            ollama_num_ctx: None,
+            ollama_options: serde_json::Map::new(),
+            generation: GenerationParams::default(),
+            default_target_language: default_target_language(),
+            alternatives: default_alternatives(),
+            fallback_providers: Vec::new(),
+            breaker_threshold: default_breaker_threshold(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
+            protect_spans: default_protect_spans(),
+            protected_patterns: Vec::new(),
+            pii_redaction: false,
+            pii_detectors: default_pii_detectors(),
+            pii_block: default_pii_block(),
+            log_filter: default_log_filter(),
+            local_only: false,
+            local_allowlist: Vec::new(),
+            privacy_mode: default_privacy_mode(),
         }
     }
 }
 
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+fn settings_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app.path().app_data_dir().map_err(|e| AppError::Storage(e.to_string()))?;
+    std::fs::create_dir_all(&dir).map_err(|e| AppError::Storage(e.to_string()))?;
     Ok(dir.join(SETTINGS_FILENAME))
 }
 
-pub fn load_settings(app: &AppHandle) -> Result<AppSettings, String> {
+/// Reads settings.json, or the defaults if there is none. A file that exists but does not parse
+/// is reported as `SettingsCorrupt` rather than silently replaced by the defaults.
+pub fn load_settings(app: &AppHandle) -> Result<AppSettings, AppError> {
     let path = settings_path(app)?;
     if !path.exists() {
         return Ok(AppSettings::default());
     }
-    let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    Ok(serde_json::from_str(&contents).unwrap_or_default())
+    let contents = std::fs::read_to_string(&path).map_err(|e| AppError::Storage(e.to_string()))?;
+    serde_json::from_str(&contents).map_err(|e| {
+        AppError::SettingsCorrupt(format!(
+            "The settings file {} is damaged ({}). Open Settings and save to replace it.",
+            path.display(),
+            e
+        ))
+    })
 }
 
-pub fn save_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+pub fn save_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), AppError> {
     let path = settings_path(app)?;
-    let contents = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
-    std::fs::write(&path, contents).map_err(|e| e.to_string())
+    let contents = serde_json::to_string_pretty(settings).map_err(|e| AppError::Storage(e.to_string()))?;
+    std::fs::write(&path, contents).map_err(|e| AppError::Storage(e.to_string()))
 }
@@ -4,9 +4,35 @@ window.addEventListener("DOMContentLoaded", () => {
   const { listen } = window.__TAURI__.event;
   const { getCurrentWindow } = window.__TAURI__.window;
 
-  function log(msg) {
+  /** Logs to the console and the log file; `level` is "info" unless given (e.g. "debug" for traces). */
+  function log(msg, level) {
     console.log(msg);
-    invoke("debug_log", { message: msg }).catch((e) => console.error("Failed to log:", e));
+    invoke("debug_log", { message: msg, level }).catch((e) => console.error("Failed to log:", e));
+  }
+
+  /**
+   * Text for an error from a command: the backend's AppError `{code, message, retryable,
+   * retry_after_secs, detail}`, or anything else thrown.
+   */
+  function errorMessage(error) {
+    if (!error || typeof error !== "object" || !error.message) return String(error);
+    let text = error.message;
+    if (error.retry_after_secs) {
+      text += " Try again in " + error.retry_after_secs + " s.";
+    } else if (error.retryable) {
+      text += " Try again in a moment.";
+    }
+    if (error.detail) text += "\n\n" + error.detail;
+    return text;
+  }
+
+  /**
+   * Error text for the log: code and message only. `detail` can hold the model's answer, which
+   * privacy mode keeps out of the log.
+   */
+  function errorSummary(error) {
+    if (!error || typeof error !== "object" || !error.message) return String(error);
+    return error.code ? error.code + ": " + error.message : error.message;
   }
 
   function escapeHtml(text) {
@@ -15,29 +41,61 @@ window.addEventListener("DOMContentLoaded", () => {
     return div.innerHTML;
   }
 
-  /** Uses jsdiff (Diff global from index.html script) to highlight added/changed words. */
-  function buildDiffHtml(original, corrected) {
-    if (typeof Diff === "undefined" || !Diff.diffWords) {
-      return escapeHtml(corrected);
-    }
-    const changes = Diff.diffWords(original, corrected); // jsdiff API
+  /** Highlights inserted and replaced words using the diff spans from the backend (UTF-16 offsets). */
+  function buildDiffHtml(corrected, spans) {
     const parts = [];
-    for (const part of changes) {
-      if (part.removed) continue;
-      const escaped = escapeHtml(part.value);
-      if (part.added) {
-        parts.push('<span class="diff-add">' + escaped + "</span>");
-      } else {
-        parts.push(escaped);
-      }
+    let pos = 0;
+    for (const span of spans) {
+      if (span.kind === "delete") continue;
+      const start = span.corrected.start_utf16;
+      const end = span.corrected.end_utf16;
+      parts.push(escapeHtml(corrected.slice(pos, start)));
+      parts.push('<span class="diff-add">' + escapeHtml(corrected.slice(start, end)) + "</span>");
+      pos = end;
     }
+    parts.push(escapeHtml(corrected.slice(pos)));
     return parts.join("");
   }
 
+  /**
+   * Lists the model's edits (category, replaced span, replacement, reason) in the explanation area,
+   * after a note for cached answers and a warning for each chunk of a long selection that could not be fixed.
+   */
+  function renderExplanation(result) {
+    const warnings = (result.chunk_errors || []).map((e) =>
+      '<div class="chunk-error">' + escapeHtml(e.message) + "</div>"
+    );
+    if (result.redactions && result.redactions.length) {
+      const counts = result.redactions.map((r) => r.label + " (" + r.count + ")");
+      warnings.unshift('<div class="cached-note">Redacted before sending: ' + escapeHtml(counts.join(", ")) + ".</div>");
+    }
+    if (result.fallback) {
+      warnings.unshift('<div class="cached-note">Main provider unavailable; answered by ' + escapeHtml(result.provider || "a fallback") + ".</div>");
+    }
+    if (result.cached) {
+      warnings.unshift('<div class="cached-note">Reused a cached answer.</div>');
+    }
+    if (result.detected_language) {
+      warnings.unshift('<div class="detected-language">Detected language: ' + escapeHtml(result.detected_language) + "</div>");
+    }
+    const edits = result.edits || [];
+    let body = escapeHtml(result.explanation || "");
+    if (edits.length) {
+      const items = edits.map((edit) =>
+        '<li><span class="edit-category">' + escapeHtml(edit.category) + "</span> " +
+        '<del class="edit-original">' + escapeHtml(edit.original) + "</del> → " +
+        '<ins class="edit-replacement">' + escapeHtml(edit.replacement) + "</ins>" +
+        (edit.reason ? " — " + escapeHtml(edit.reason) : "") + "</li>"
+      );
+      body = '<ul class="edit-list">' + items.join("") + "</ul>";
+    }
+    explanationDiv.innerHTML = warnings.join("") + body;
+  }
+
   window.addEventListener("click", (e) => {
-    log(`Click at: ${e.clientX}, ${e.clientY} Target: ${e.target.tagName}#${e.target.id || ""}`);
+    log(`Click at: ${e.clientX}, ${e.clientY} Target: ${e.target.tagName}#${e.target.id || ""}`, "debug");
   });
-  log("Frontend JS Loaded");
+  log("Frontend JS Loaded", "debug");
 
   let originalText = "";
   let correctedText = "";
@@ -47,30 +105,114 @@ window.addEventListener("DOMContentLoaded", () => {
   const copyInlineBtn = document.getElementById("copy-inline-btn");
   const explanationDiv = document.getElementById("explanation");
   const fixBtn = document.getElementById("fix-btn");
+  const actionSelect = document.getElementById("action-select");
+  const translateOptions = document.getElementById("translate-options");
+  const sourceLanguageInput = document.getElementById("source-language");
+  const targetLanguageInput = document.getElementById("target-language");
+  const translateFixGrammar = document.getElementById("translate-fix-grammar");
   const loadingDiv = document.getElementById("loading");
+  const alternativesDiv = document.getElementById("alternatives");
+  const useBtn = document.getElementById("use-btn");
 
-  function setCorrectedContent(plainText, originalForDiff) {
+  function setCorrectedContent(plainText, diffSpans) {
     correctedText = plainText;
+    useBtn.classList.toggle("hidden", !plainText);
     if (plainText) {
       copyInlineBtn.classList.remove("hidden");
-      const original = originalForDiff != null ? originalForDiff : originalTextArea.value.trim();
-      correctedPreview.innerHTML = original ? buildDiffHtml(original, plainText) : escapeHtml(plainText);
+      correctedPreview.innerHTML = diffSpans ? buildDiffHtml(plainText, diffSpans) : escapeHtml(plainText);
     } else {
       copyInlineBtn.classList.add("hidden");
       correctedPreview.innerHTML = "";
     }
   }
 
+  /** One tab per labeled alternative; choosing a tab shows that candidate in the preview. */
+  function renderAlternatives(candidates) {
+    alternativesDiv.innerHTML = "";
+    alternativesDiv.classList.toggle("hidden", candidates.length < 2);
+    candidates.forEach((candidate, i) => {
+      const tab = document.createElement("button");
+      tab.type = "button";
+      tab.className = "alternative-tab" + (i === 0 ? " active" : "");
+      tab.setAttribute("role", "tab");
+      tab.textContent = candidate.label;
+      tab.addEventListener("click", () => {
+        for (const other of alternativesDiv.children) other.classList.remove("active");
+        tab.classList.add("active");
+        setCorrectedContent(candidate.corrected, candidate.diff);
+      });
+      alternativesDiv.appendChild(tab);
+    });
+  }
+
   // Listen for text from backend
   listen("set-text", (event) => {
     const text = event.payload;
     originalText = text;
     originalTextArea.value = text;
+    renderAlternatives([]);
     setCorrectedContent("");
     explanationDiv.innerText = "";
     updateFixButtonState();
   });
 
+  // Text of the fix currently in flight; partial results from other runs are ignored.
+  let pendingFixText = null;
+  // ID of the fix in flight, passed to cancel_fix_command.
+  let pendingRequestId = null;
+  let nextRequestId = 1;
+  /** Stops the running fix, if any. The fix's own invoke then rejects with code "cancelled". */
+  async function cancelPendingFix() {
+    if (pendingRequestId == null) return;
+    try {
+      await invoke("cancel_fix_command", { requestId: pendingRequestId });
+    } catch (e) {
+      log("Cancel failed: " + errorSummary(e));
+    }
+  }
+
+  // Partial corrected text while a streamed fix is running
+  listen("fix-progress", (event) => {
+    if (pendingFixText == null) return;
+    loadingDiv.classList.add("hidden");
+    setCorrectedContent(event.payload);
+  });
+
+  // Fill the action picker from the backend registry; "fix" stays selected by default.
+  invoke("list_actions_command")
+    .then((actions) => {
+      actionSelect.innerHTML = "";
+      for (const action of actions) {
+        const option = document.createElement("option");
+        option.value = action.id;
+        option.textContent = action.label;
+        actionSelect.appendChild(option);
+      }
+      actionSelect.value = "fix";
+    })
+    .catch((e) => log("Failed to load actions: " + errorSummary(e)));
+
+  // Default translation target from settings; the popup can override it per run.
+  invoke("get_settings_command")
+    .then((s) => { targetLanguageInput.value = s.default_target_language || ""; })
+    .catch((e) => log("Failed to load settings: " + errorSummary(e)));
+
+  actionSelect.addEventListener("change", () => {
+    const action = actionSelect.value;
+    fixBtn.textContent = action === "fix" ? "Fix" : action === "translate" ? "Translate" : "Rewrite";
+    translateOptions.classList.toggle("hidden", action !== "translate");
+  });
+
+  /** Per-run options for the selected action (only "translate" takes any). */
+  function actionOptions() {
+    if (actionSelect.value !== "translate") return null;
+    return {
+      source_language: sourceLanguageInput.value.trim() || null,
+      target_language: targetLanguageInput.value.trim() || null,
+      fix_grammar: translateFixGrammar.checked,
+    };
+  }
+
   function updateFixButtonState() {
     fixBtn.disabled = !originalTextArea.value.trim();
   }
@@ -87,24 +229,44 @@ window.addEventListener("DOMContentLoaded", () => {
   updateFixButtonState();
 
   fixBtn.addEventListener("click", async () => {
-    log("Fix Button Clicked");
+    log("Fix Button Clicked", "debug");
     const textToFix = originalTextArea.value.trim();
     if (!textToFix) {
       alert("Please enter or paste text to fix.");
       return;
     }
 
+    await cancelPendingFix();
+    const requestId = nextRequestId++;
+    renderAlternatives([]);
     loadingDiv.classList.remove("hidden");
+    pendingFixText = textToFix;
+    pendingRequestId = requestId;
     try {
-      const result = await invoke("fix_grammar_command", { text: textToFix });
+      const result = await invoke("run_action_command", {
+        action: actionSelect.value,
+        text: textToFix,
+        options: actionOptions(),
+        requestId,
+      });
       correctedText = result.corrected;
-      setCorrectedContent(result.corrected, textToFix);
-      explanationDiv.innerText = result.explanation || "";
+      renderAlternatives(result.alternatives || []);
+      setCorrectedContent(result.corrected, result.diff);
+      renderExplanation(result);
     } catch (error) {
-      log("Error fixing grammar: " + error);
-      alert("Error fixing grammar: " + error);
+      if (error?.code === "cancelled") {
+        log("Fix cancelled");
+      } else {
+        log("Error running " + actionSelect.value + ": " + errorSummary(error));
+        alert("Error: " + errorMessage(error));
+      }
     } finally {
-      loadingDiv.classList.add("hidden");
+      // A newer fix may have started while this one was being cancelled.
+      if (pendingRequestId === requestId) {
+        pendingFixText = null;
+        pendingRequestId = null;
+        loadingDiv.classList.add("hidden");
+      }
     }
   });
 
@@ -116,22 +278,37 @@ window.addEventListener("DOMContentLoaded", () => {
       copyInlineBtn.textContent = "Copied!";
       setTimeout(() => { copyInlineBtn.textContent = label; }, 1500);
     } catch (e) {
-      log("Copy failed: " + e);
+      log("Copy failed: " + errorSummary(e));
       alert("Failed to copy to clipboard.");
     }
   });
 
+  // Pastes the suggestion shown in the preview over the original selection and closes the popup.
+  useBtn.addEventListener("click", async () => {
+    if (!correctedText) return;
+    try {
+      await invoke("commit_candidate_command", { text: correctedText });
+    } catch (e) {
+      log("Commit failed: " + errorSummary(e));
+      alert("Failed to apply suggestion: " + errorMessage(e));
+    }
+  });
+
+  document.getElementById("loading-cancel-btn").addEventListener("click", cancelPendingFix);
+
   document.getElementById("cancel-btn").addEventListener("click", async () => {
+    await cancelPendingFix();
     await getCurrentWindow().hide();
   });
 
   const closeBtn = document.getElementById("close-btn");
   closeBtn.addEventListener("click", async () => {
+    await cancelPendingFix();
     try {
       await getCurrentWindow().hide();
     } catch (e) {
       console.error("Failed to hide window:", e);
-      alert("Failed to close: " + e);
+      alert("Failed to close: " + errorMessage(e));
     }
   });
 
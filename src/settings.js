@@ -9,32 +9,258 @@
   const invoke = core.invoke.bind(core);
   const getCurrentWindow = tauriWindow?.getCurrentWindow || (() => ({ hide: async () => {} }));
 
+  /** Message of an AppError from a command (`{code, message, ...}`), or the thrown value as text. */
+  function errorMessage(error) {
+    return error && typeof error === "object" && error.message ? error.message : String(error);
+  }
+
+  const PII_MODES = [
+    { value: "off", label: "Send as is" },
+    { value: "redact", label: "Redact" },
+    { value: "block", label: "Block" },
+  ];
+
+  for (const select of document.querySelectorAll(".pii-detector")) {
+    for (const mode of PII_MODES) {
+      const option = document.createElement("option");
+      option.value = mode.value;
+      option.textContent = mode.label;
+      select.appendChild(option);
+    }
+  }
+
+  const CLOUD_PROVIDERS = ["openai", "gemini", "anthropic"];
+
+  /**
+   * Local-only mode locks the provider selects. A cloud provider could never pass the host
+   * check, so it is switched to Ollama first.
+   */
+  function updateLocalOnly() {
+    const localOnly = document.getElementById("local-only").checked;
+    const provider = document.getElementById("ai-provider");
+    if (localOnly && CLOUD_PROVIDERS.includes(provider.value)) {
+      provider.value = "ollama";
+      updateBaseUrlVisibility();
+    }
+    provider.disabled = localOnly;
+    for (const select of document.querySelectorAll(".fallback-provider")) select.disabled = localOnly;
+    document.getElementById("local-allowlist-field").style.display = localOnly ? "" : "none";
+  }
+
+  /** Sets each detector's select from the `pii_detectors` and `pii_block` lists. */
+  function loadPiiModes(detectors, block) {
+    for (const select of document.querySelectorAll(".pii-detector")) {
+      const id = select.dataset.detector;
+      select.value = block.includes(id) ? "block" : detectors.includes(id) ? "redact" : "off";
+    }
+  }
+
+  /** The detector selects as `pii_detectors` (redacted or blocked) and `pii_block`. */
+  function collectPiiModes() {
+    const selects = [...document.querySelectorAll(".pii-detector")];
+    return {
+      pii_detectors: selects.filter((s) => s.value !== "off").map((s) => s.dataset.detector),
+      pii_block: selects.filter((s) => s.value === "block").map((s) => s.dataset.detector),
+    };
+  }
+
   function updateBaseUrlVisibility() {
     const provider = document.getElementById("ai-provider").value;
     const field = document.getElementById("api-base-field");
-    if (provider === "openai" || provider === "gemini") {
+    if (provider === "openai" || provider === "gemini" || provider === "anthropic") {
       field.style.display = "none";
     } else {
       field.style.display = "";
     }
+    document.getElementById("ollama-fields").style.display = provider === "ollama" ? "" : "none";
+  }
+
+  /** Number input value, or null when empty so the parameter is not sent. */
+  function optionalNumber(id, parse) {
+    const value = parse(document.getElementById(id).value);
+    return Number.isNaN(value) ? null : value;
+  }
+
+  function collectGeneration() {
+    return {
+      temperature: optionalNumber("gen-temperature", parseFloat),
+      top_p: optionalNumber("gen-top-p", parseFloat),
+      max_tokens: optionalNumber("gen-max-tokens", (v) => parseInt(v, 10)),
+      seed: optionalNumber("gen-seed", (v) => parseInt(v, 10)),
+      stop: document.getElementById("gen-stop").value.split(",").map((s) => s.trim()).filter(Boolean),
+      presence_penalty: optionalNumber("gen-presence-penalty", parseFloat),
+      frequency_penalty: optionalNumber("gen-frequency-penalty", parseFloat),
+    };
+  }
+
+  /** Parses the Ollama options textarea; empty means no extra options. */
+  function parseOllamaOptions() {
+    const text = document.getElementById("ollama-options").value.trim();
+    if (!text) return {};
+    let options;
+    try {
+      options = JSON.parse(text);
+    } catch (e) {
+      throw new Error("Ollama options are not valid JSON: " + e.message);
+    }
+    if (options === null || typeof options !== "object" || Array.isArray(options)) {
+      throw new Error("Ollama options must be a JSON object.");
+    }
+    return options;
+  }
+
+  const PROVIDER_OPTIONS = [
+    ["openai", "OpenAI"],
+    ["gemini", "Gemini"],
+    ["anthropic", "Anthropic"],
+    ["ollama", "Ollama"],
+    ["custom", "Custom"],
+  ];
+
+  /** Appends an editable row for one fallback provider. */
+  function addFallbackRow(fallback) {
+    const row = document.createElement("div");
+    row.className = "fallback-row";
+    const provider = document.createElement("select");
+    provider.className = "fallback-provider";
+    provider.setAttribute("aria-label", "Fallback provider");
+    for (const [value, label] of PROVIDER_OPTIONS) {
+      const option = document.createElement("option");
+      option.value = value;
+      option.textContent = label;
+      provider.appendChild(option);
+    }
+    provider.value = fallback.ai_provider || "ollama";
+    provider.disabled = document.getElementById("local-only").checked;
+    const input = (className, placeholder, value, type) => {
+      const el = document.createElement("input");
+      el.type = type || "text";
+      el.className = className;
+      el.placeholder = placeholder;
+      el.autocomplete = "off";
+      el.value = value || "";
+      return el;
+    };
+    const base = input("fallback-base", "Base URL (Ollama/Custom)", fallback.api_base);
+    const key = input("fallback-key", "API key", fallback.api_key, "password");
+    const model = input("fallback-model", "Model", fallback.model);
+    const remove = document.createElement("button");
+    remove.type = "button";
+    remove.className = "btn btn-secondary";
+    remove.textContent = "Remove";
+    remove.addEventListener("click", () => row.remove());
+    row.append(provider, base, key, model, remove);
+    document.getElementById("fallback-list").appendChild(row);
+  }
+
+  function collectFallbacks() {
+    return [...document.querySelectorAll("#fallback-list .fallback-row")].map((row) => ({
+      ai_provider: row.querySelector(".fallback-provider").value,
+      api_base: row.querySelector(".fallback-base").value.trim(),
+      api_key: row.querySelector(".fallback-key").value,
+      model: row.querySelector(".fallback-model").value.trim(),
+    }));
+  }
+
+  document.getElementById("add-fallback-btn").addEventListener("click", () => addFallbackRow({}));
+
+  // Last settings loaded from the backend; fields without a form control are saved back unchanged.
+  let loadedSettings = {};
+
+  /** Current form values merged over the loaded settings. */
+  function collectSettings() {
+    return {
+      ...loadedSettings,
+      ai_provider: document.getElementById("ai-provider").value,
+      api_base: document.getElementById("api-base").value.trim(),
+      api_key: document.getElementById("api-key").value,
+      model: document.getElementById("model").value.trim(),
+      system_prompt: document.getElementById("system-prompt").value.trim(),
+      hotkey: document.getElementById("hotkey-input").value.trim() || "Ctrl+Shift+Space",
+      stream_responses: document.getElementById("stream-responses").checked,
+      explain_edits: document.getElementById("explain-edits").checked,
+      chunk_size: parseInt(document.getElementById("chunk-size").value, 10) || loadedSettings.chunk_size,
+      chunk_concurrency: parseInt(document.getElementById("chunk-concurrency").value, 10) || loadedSettings.chunk_concurrency,
+      retry_max_attempts: parseInt(document.getElementById("retry-max-attempts").value, 10) || loadedSettings.retry_max_attempts,
+      retry_max_wait_secs: parseInt(document.getElementById("retry-max-wait").value, 10) || 0,
+      cache_enabled: document.getElementById("cache-enabled").checked,
+      cache_ttl_hours: parseInt(document.getElementById("cache-ttl-hours").value, 10) || loadedSettings.cache_ttl_hours,
+      cache_max_entries: parseInt(document.getElementById("cache-max-entries").value, 10) || loadedSettings.cache_max_entries,
+      ollama_keep_alive: document.getElementById("ollama-keep-alive").value.trim(),
+      ollama_num_ctx: parseInt(document.getElementById("ollama-num-ctx").value, 10) || null,
+      ollama_options: parseOllamaOptions(),
+      generation: collectGeneration(),
+      default_target_language: document.getElementById("default-target-language").value.trim(),
+      alternatives: parseInt(document.getElementById("alternatives").value, 10) || loadedSettings.alternatives,
+      fallback_providers: collectFallbacks(),
+      protect_spans: document.getElementById("protect-spans").checked,
+      protected_patterns: document.getElementById("protected-patterns").value.split("\n").map((s) => s.trim()).filter(Boolean),
+      breaker_threshold: optionalNumber("breaker-threshold", (v) => parseInt(v, 10)) ?? loadedSettings.breaker_threshold,
+      breaker_cooldown_secs: parseInt(document.getElementById("breaker-cooldown").value, 10) || loadedSettings.breaker_cooldown_secs,
+      log_filter: document.getElementById("log-filter").value.trim() || "info",
+      privacy_mode: document.getElementById("privacy-mode").checked,
+      local_only: document.getElementById("local-only").checked,
+      local_allowlist: document.getElementById("local-allowlist").value.split("\n").map((s) => s.trim()).filter(Boolean),
+      pii_redaction: document.getElementById("pii-redaction").checked,
+      ...collectPiiModes(),
+    };
   }
 
   async function loadSettings() {
     try {
       const s = await invoke("get_settings_command");
+      loadedSettings = s;
       document.getElementById("ai-provider").value = s.ai_provider || "ollama";
       document.getElementById("api-base").value = s.api_base || "";
       document.getElementById("api-key").value = s.api_key || "";
       document.getElementById("model").value = s.model || "";
       document.getElementById("system-prompt").value = s.system_prompt || "";
       document.getElementById("hotkey-input").value = s.hotkey || "Ctrl+Shift+Space";
+      document.getElementById("stream-responses").checked = !!s.stream_responses;
+      document.getElementById("explain-edits").checked = !!s.explain_edits;
+      document.getElementById("protect-spans").checked = !!s.protect_spans;
+      document.getElementById("protected-patterns").value = (s.protected_patterns || []).join("\n");
+      document.getElementById("chunk-size").value = s.chunk_size;
+      document.getElementById("chunk-concurrency").value = s.chunk_concurrency;
+      document.getElementById("retry-max-attempts").value = s.retry_max_attempts;
+      document.getElementById("retry-max-wait").value = s.retry_max_wait_secs;
+      document.getElementById("cache-enabled").checked = !!s.cache_enabled;
+      document.getElementById("cache-ttl-hours").value = s.cache_ttl_hours;
+      document.getElementById("cache-max-entries").value = s.cache_max_entries;
+      document.getElementById("ollama-keep-alive").value = s.ollama_keep_alive || "";
+      document.getElementById("ollama-num-ctx").value = s.ollama_num_ctx ?? "";
+      const ollamaOptions = s.ollama_options || {};
+      document.getElementById("ollama-options").value = Object.keys(ollamaOptions).length ? JSON.stringify(ollamaOptions) : "";
+      document.getElementById("default-target-language").value = s.default_target_language || "";
+      document.getElementById("alternatives").value = s.alternatives;
+      document.getElementById("fallback-list").innerHTML = "";
+      for (const fallback of s.fallback_providers || []) addFallbackRow(fallback);
+      document.getElementById("breaker-threshold").value = s.breaker_threshold;
+      document.getElementById("breaker-cooldown").value = s.breaker_cooldown_secs;
+      document.getElementById("log-filter").value = s.log_filter || "info";
+      document.getElementById("privacy-mode").checked = s.privacy_mode !== false;
+      document.getElementById("local-only").checked = !!s.local_only;
+      document.getElementById("local-allowlist").value = (s.local_allowlist || []).join("\n");
+      document.getElementById("pii-redaction").checked = !!s.pii_redaction;
+      loadPiiModes(s.pii_detectors || [], s.pii_block || []);
+      const gen = s.generation || {};
+      document.getElementById("gen-temperature").value = gen.temperature ?? "";
+      document.getElementById("gen-top-p").value = gen.top_p ?? "";
+      document.getElementById("gen-max-tokens").value = gen.max_tokens ?? "";
+      document.getElementById("gen-seed").value = gen.seed ?? "";
+      document.getElementById("gen-stop").value = (gen.stop || []).join(", ");
+      document.getElementById("gen-presence-penalty").value = gen.presence_penalty ?? "";
+      document.getElementById("gen-frequency-penalty").value = gen.frequency_penalty ?? "";
       updateBaseUrlVisibility();
+      updateLocalOnly();
     } catch (e) {
       console.error("Failed to load settings:", e);
+      if (e?.code === "settings_corrupt") alert(errorMessage(e));
     }
   }
 
   document.getElementById("ai-provider").addEventListener("change", updateBaseUrlVisibility);
+  document.getElementById("local-only").addEventListener("change", updateLocalOnly);
 
   function eventToShortcutString(evt) {
     const parts = [];
@@ -78,16 +304,7 @@
     const saveBtn = document.getElementById("save-btn");
     saveBtn.disabled = true;
     try {
-      await invoke("save_settings_command", {
-        settings: {
-          ai_provider: document.getElementById("ai-provider").value,
-          api_base: document.getElementById("api-base").value.trim(),
-          api_key: document.getElementById("api-key").value,
-          model: document.getElementById("model").value.trim(),
-          system_prompt: document.getElementById("system-prompt").value.trim(),
-          hotkey: document.getElementById("hotkey-input").value.trim() || "Ctrl+Shift+Space",
-        },
-      });
+      await invoke("save_settings_command", { settings: collectSettings() });
       const w = getCurrentWindow();
       if (w && typeof w.hide === "function") {
         await w.hide();
@@ -96,7 +313,7 @@
       }
     } catch (err) {
       console.error("Failed to save settings:", err);
-      alert("Failed to save: " + String(err));
+      alert("Failed to save: " + errorMessage(err));
       // Keep settings window open so user can change the shortcut
     } finally {
       saveBtn.disabled = false;
@@ -165,24 +382,122 @@
     testStatus.innerHTML = "";
     testStatus.className = "test-status";
     try {
-      await invoke("test_ai_connection_command", {
-        settings: {
-          ai_provider: document.getElementById("ai-provider").value,
-          api_base: document.getElementById("api-base").value.trim(),
-          api_key: document.getElementById("api-key").value,
-          model: document.getElementById("model").value.trim(),
-          system_prompt: document.getElementById("system-prompt").value.trim(),
-        },
-      });
+      await invoke("test_ai_connection_command", { settings: collectSettings() });
       testStatus.innerHTML = checkSvg + " <span>Connection OK</span>";
       testStatus.classList.add("test-status-ok");
     } catch (err) {
-      testStatus.innerHTML = crossSvg + " <span>" + String(err).replace(/</g, "&lt;") + "</span>";
+      testStatus.innerHTML = crossSvg + " <span>" + errorMessage(err).replace(/</g, "&lt;") + "</span>";
       testStatus.classList.add("test-status-fail");
     } finally {
       testBtn.disabled = false;
     }
   });
 
+  /** Short capability summary for a model option, e.g. "128k context · JSON · vision". */
+  function describeModel(model) {
+    const parts = [];
+    if (model.display_name && model.display_name !== model.id) parts.push(model.display_name);
+    if (model.context_length) {
+      const k = model.context_length / 1000;
+      parts.push((k >= 1 ? Math.round(k) + "k" : model.context_length) + " context");
+    }
+    if (model.json_mode) parts.push("JSON");
+    if (model.vision) parts.push("vision");
+    return parts.join(" · ");
+  }
+
+  // Models from the last "Load models", by ID, to describe the selected one.
+  let loadedModels = {};
+
+  function updateModelHint() {
+    const model = loadedModels[document.getElementById("model").value.trim()];
+    const hint = document.getElementById("model-hint");
+    if (model) {
+      hint.textContent = describeModel(model) || "No capability details reported.";
+    } else if (Object.keys(loadedModels).length) {
+      hint.textContent = "Not in the provider's model list.";
+    } else {
+      hint.textContent = "Model name for chat completions. Load models to pick from the provider's list.";
+    }
+  }
+
+  document.getElementById("model").addEventListener("input", updateModelHint);
+  // A different provider has different models.
+  document.getElementById("ai-provider").addEventListener("change", () => {
+    loadedModels = {};
+    document.getElementById("model-options").innerHTML = "";
+    updateModelHint();
+  });
+
+  document.getElementById("load-models-btn").addEventListener("click", async () => {
+    const btn = document.getElementById("load-models-btn");
+    const hint = document.getElementById("model-hint");
+    btn.disabled = true;
+    try {
+      const models = await invoke("list_models_command", { settings: collectSettings() });
+      const list = document.getElementById("model-options");
+      list.innerHTML = "";
+      loadedModels = {};
+      for (const model of models) {
+        loadedModels[model.id] = model;
+        const option = document.createElement("option");
+        option.value = model.id;
+        option.label = describeModel(model);
+        list.appendChild(option);
+      }
+      if (models.length) {
+        updateModelHint();
+      } else {
+        hint.textContent = "The provider returned no models.";
+      }
+    } catch (err) {
+      hint.textContent = "Failed to load models: " + errorMessage(err);
+    } finally {
+      btn.disabled = false;
+    }
+  });
+
+  document.getElementById("clear-cache-btn").addEventListener("click", async () => {
+    const btn = document.getElementById("clear-cache-btn");
+    btn.disabled = true;
+    try {
+      await invoke("clear_cache_command");
+      btn.textContent = "Cleared";
+      setTimeout(() => { btn.textContent = "Clear cache"; }, 1500);
+    } catch (err) {
+      alert("Failed to clear cache: " + errorMessage(err));
+    } finally {
+      btn.disabled = false;
+    }
+  });
+
+  /** One log entry as a line: local time, level, module, message. */
+  function formatLogEntry(entry) {
+    const time = new Date(entry.ts).toLocaleString();
+    return time + "  " + entry.level.padEnd(5) + " " + entry.target + ": " + entry.message;
+  }
+
+  document.getElementById("show-log-btn").addEventListener("click", async () => {
+    const btn = document.getElementById("show-log-btn");
+    const view = document.getElementById("log-view");
+    if (!view.hidden) {
+      view.hidden = true;
+      btn.textContent = "Show log";
+      return;
+    }
+    btn.disabled = true;
+    try {
+      const entries = await invoke("get_recent_logs_command", { limit: 200 });
+      view.textContent = entries.length ? entries.map(formatLogEntry).join("\n") : "The log is empty.";
+      view.hidden = false;
+      view.scrollTop = view.scrollHeight;
+      btn.textContent = "Hide log";
+    } catch (err) {
+      alert("Failed to read the log: " + errorMessage(err));
+    } finally {
+      btn.disabled = false;
+    }
+  });
+
   loadSettings();
 })();